@@ -1,4 +1,6 @@
 use common::bot::Bot;
+use common::commands::deploy::DeployOptions;
+use common::commands::errors::ErrorReportTarget;
 use data::Database;
 use embark_id_sync::EmbarkIDSync;
 use std::sync::Arc;
@@ -20,6 +22,9 @@ async fn main() {
 
     let mut bot = Bot::new(token);
 
+    // Log the command changes without sending them to Discord
+    let dry_run = env::var("COMMANDS_DRY_RUN").is_ok_and(|value| value == "1" || value == "true");
+
     let database_path = match env::var("DATABASE_PATH".to_string()) {
         Ok(database_path) => database_path,
         Err(_) => {
@@ -30,13 +35,67 @@ async fn main() {
 
     let database = Arc::new(Database::new(database_path).expect("Error with the database!"));
 
-    let embark_id_sync = EmbarkIDSync::new(database).await;
+    // Comma separated guild ids to deploy global commands to while developing
+    let dev_guilds = env_ids("DEV_GUILD_IDS");
+    // Remembered so their dev copies are removed once they are no longer dev guilds
+    let former_dev_guilds = database
+        .get_dev_guilds()
+        .into_iter()
+        .filter(|guild_id| !dev_guilds.contains(guild_id))
+        .collect();
+    if let Err(error) = database.add_dev_guilds(&dev_guilds) {
+        error!("Could not save dev guilds: {}", error);
+    }
+
+    bot.deploy_options(DeployOptions {
+        dry_run,
+        dev_guilds,
+        former_dev_guilds,
+    });
+
+    // Where failures are reported with their reference id, a channel or the owner's DMs
+    if let Some(channel_id) = env_id("ERROR_REPORT_CHANNEL_ID") {
+        bot.report_errors_to(ErrorReportTarget::Channel(channel_id));
+    } else if let Some(user_id) = env_id("ERROR_REPORT_USER_ID") {
+        bot.report_errors_to(ErrorReportTarget::Owner(user_id));
+    }
+
+    let embark_id_sync = EmbarkIDSync::new(database);
 
     bot.register(embark_id_sync);
 
     bot.start_blocking().await;
 }
 
+/// Comma separated ids from an environment variable, logging values that aren't one
+fn env_ids<T>(name: &str) -> Vec<Id<T>> {
+    let Ok(ids) = env::var(name) else {
+        return Vec::new();
+    };
+
+    ids.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .filter_map(|id| match id.parse().ok().and_then(Id::new_checked) {
+            Some(id) => Some(id),
+            None => {
+                error!("Ignoring invalid id in {}: {}", name, id);
+                None
+            }
+        })
+        .collect()
+}
+
+/// An id from an environment variable, logging values that aren't one
+fn env_id<T>(name: &str) -> Option<Id<T>> {
+    let value = env::var(name).ok()?;
+    let id = value.trim().parse().ok().and_then(Id::new_checked);
+    if id.is_none() {
+        error!("Ignoring invalid id in {}: {}", name, value);
+    }
+    id
+}
+
 pub fn register_tracing() -> tracing_appender::non_blocking::WorkerGuard {
     let file_appender = rolling::daily("logs", "log");
     let (non_blocking_writer, guard) = tracing_appender::non_blocking(file_appender);
@@ -1,8 +1,10 @@
+use crate::commands::deploy::DeployOptions;
+use crate::commands::errors::ErrorReportTarget;
 use crate::commands::registry::CommandRegistry;
 use crate::context::Context;
 use crate::handler::Handler;
 use std::sync::Arc;
-use tracing::{debug, info};
+use tracing::{debug, error, info};
 use twilight_cache_inmemory::{CacheableStageInstance, DefaultInMemoryCache};
 use twilight_gateway::{Event, EventTypeFlags, Intents, Shard, ShardId, StreamExt};
 use twilight_http::Client;
@@ -10,6 +12,8 @@ use twilight_http::Client;
 pub struct Bot {
     handlers: Vec<Arc<Box<dyn Handler + Send>>>,
     token: String,
+    deploy_options: DeployOptions,
+    report_errors_to: Option<ErrorReportTarget>,
 }
 
 impl Bot {
@@ -17,9 +21,21 @@ impl Bot {
         Self {
             handlers: Vec::new(),
             token,
+            deploy_options: DeployOptions::default(),
+            report_errors_to: None,
         }
     }
 
+    /// Change how commands are deployed on startup, e.g. for a dry run
+    pub fn deploy_options(&mut self, options: DeployOptions) {
+        self.deploy_options = options;
+    }
+
+    /// Report interaction failures with their reference id to the owner or a channel
+    pub fn report_errors_to(&mut self, target: ErrorReportTarget) {
+        self.report_errors_to = Some(target);
+    }
+
     pub fn register<H: Handler + Send + 'static>(&mut self, handler: H) {
         self.handlers.push(Arc::new(Box::new(handler)));
     }
@@ -53,24 +69,18 @@ impl Bot {
                 .bot
                 .expect("Not bot associated with the discord app ID");
 
-            let context = Arc::new(Context {
-                client,
-                cache,
-                application_id,
-                bot,
-            });
+            let context = Arc::new(Context::new(client, cache, application_id, bot));
 
             info!("Connecting...");
 
             let mut shard = Shard::new(ShardId::ONE, self.token.to_string(), Intents::all());
 
-            self.ready_commands(&context)
-                .await
-                .expect("Couldn't ready commands");
+            self.ready_commands(&context).await;
 
             while let Some(Ok(event)) = shard.next_event(EventTypeFlags::all()).await {
                 let event = Arc::new(event);
 
+                context.standby.process(&event);
                 self.dispatch(Arc::clone(&context), Arc::clone(&event));
 
                 context.cache.update(&*event);
@@ -84,35 +94,54 @@ impl Bot {
         });
     }
 
-    async fn ready_commands(
-        &mut self,
-        context: &Context,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    /// Register every handler's commands, components and modals and deploy the commands
+    ///
+    /// Problems are logged instead of stopping the bot. If any registration was rejected nothing
+    /// is deployed, so Discord keeps the last working set of commands.
+    async fn ready_commands(&mut self, context: &Context) {
         let mut command_registry = CommandRegistry::new();
-        let mut all_registrations = Vec::new();
+        if let Some(target) = self.report_errors_to {
+            command_registry.report_errors_to(target);
+        }
+        let mut errors = Vec::new();
 
         info!("Registering commands from handlers...");
 
-        // Register commands from handlers (new system)
         for handler in &self.handlers {
             let registrations = handler.commands();
 
             for command_registration in &registrations {
                 info!(
-                    "Registering command from handler: {}",
+                    "Registering command from handler {}: {}",
+                    handler.name(),
                     command_registration.command.name()
                 );
             }
 
-            all_registrations.extend(registrations);
+            errors.extend(command_registry.register_commands(handler.name(), registrations));
+            errors
+                .extend(command_registry.register_components(handler.name(), handler.components()));
+            errors.extend(command_registry.register_modals(handler.name(), handler.modals()));
+            command_registry.add_layers(handler.middleware());
         }
 
-        command_registry.register_commands(all_registrations);
-        command_registry.deploy(&context).await?;
+        errors.extend(command_registry.check_limits());
 
-        self.register(command_registry);
+        if errors.is_empty() {
+            if let Err(error) = command_registry.deploy(context, &self.deploy_options).await {
+                error!("Could not deploy commands: {}", error);
+            }
+        } else {
+            for error in &errors {
+                error!("{}", error);
+            }
+            error!(
+                "Not deploying commands, {} registration problems",
+                errors.len()
+            );
+        }
 
-        Ok(())
+        self.register(command_registry);
     }
 
     fn dispatch(&self, context: Arc<Context>, event: Arc<Event>) {
@@ -0,0 +1,147 @@
+use super::{CommandContext, CommandError};
+use twilight_model::application::command::Command as ApplicationCommand;
+use twilight_model::application::interaction::InteractionContextType;
+use twilight_model::guild::Permissions;
+use twilight_model::oauth::ApplicationIntegrationType;
+
+/// Who may use a command and where
+///
+/// The registry copies this onto the definition before deploying and checks it again when the
+/// command is invoked, since guild admins can override the defaults Discord enforces.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandAccess {
+    /// Permissions a member needs, `None` lets everyone use the command
+    pub default_member_permissions: Option<Permissions>,
+    /// Where the command can be used, `None` means every context
+    pub contexts: Option<Vec<InteractionContextType>>,
+    /// How the app has to be installed for the command to show up, `None` leaves it to Discord
+    pub integration_types: Option<Vec<ApplicationIntegrationType>>,
+    pub nsfw: bool,
+}
+
+impl CommandAccess {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only members with all of these permissions (or administrators) can use the command
+    pub fn permissions(mut self, permissions: Permissions) -> Self {
+        self.default_member_permissions = Some(permissions);
+        self
+    }
+
+    pub fn contexts(mut self, contexts: impl IntoIterator<Item = InteractionContextType>) -> Self {
+        self.contexts = Some(contexts.into_iter().collect());
+        self
+    }
+
+    /// Shorthand for a command that can only be used in guilds
+    pub fn guild_only(self) -> Self {
+        self.contexts([InteractionContextType::Guild])
+    }
+
+    pub fn integration_types(
+        mut self,
+        integration_types: impl IntoIterator<Item = ApplicationIntegrationType>,
+    ) -> Self {
+        self.integration_types = Some(integration_types.into_iter().collect());
+        self
+    }
+
+    pub fn nsfw(mut self, nsfw: bool) -> Self {
+        self.nsfw = nsfw;
+        self
+    }
+
+    /// Copy these settings onto a command definition
+    pub fn apply(&self, command: &mut ApplicationCommand) {
+        command.default_member_permissions = self.default_member_permissions;
+        command.contexts = self.contexts.clone();
+        command.integration_types = self.integration_types.clone();
+        command.nsfw = self.nsfw.then_some(true);
+    }
+
+    /// Whether the invoker of an interaction may use the command
+    pub fn check(&self, context: &CommandContext) -> Result<(), CommandError> {
+        self.check_context(context)?;
+        self.check_permissions(context)
+    }
+
+    /// Whether the interaction happened somewhere the command can be used
+    pub fn check_context(&self, context: &CommandContext) -> Result<(), CommandError> {
+        let interaction_context = interaction_context(context);
+
+        let allowed = self
+            .contexts
+            .as_ref()
+            .is_none_or(|contexts| contexts.contains(&interaction_context));
+        if allowed {
+            return Ok(());
+        }
+
+        Err(CommandError::Validation(match interaction_context {
+            InteractionContextType::Guild => "This command can't be used in a guild".into(),
+            _ => "This command must be done in a guild!".into(),
+        }))
+    }
+
+    /// Whether the invoker has the permissions the command needs
+    pub fn check_permissions(&self, context: &CommandContext) -> Result<(), CommandError> {
+        // Discord doesn't apply member permissions outside of guilds
+        if interaction_context(context) != InteractionContextType::Guild {
+            return Ok(());
+        }
+
+        let Some(required) = self.default_member_permissions else {
+            return Ok(());
+        };
+        let permissions = context
+            .get_member_permissions()
+            .unwrap_or(Permissions::empty());
+
+        if permissions.contains(Permissions::ADMINISTRATOR) || permissions.contains(required) {
+            Ok(())
+        } else {
+            Err(CommandError::Validation(format!(
+                "You need the {} permission for this",
+                permission_names(required - permissions)
+            )))
+        }
+    }
+}
+
+/// Where an interaction happened
+fn interaction_context(context: &CommandContext) -> InteractionContextType {
+    // Older interactions don't say where they happened
+    let guessed = match context.get_guild_id() {
+        Some(_) => InteractionContextType::Guild,
+        None => InteractionContextType::BotDm,
+    };
+    context.get_interaction_context().unwrap_or(guessed)
+}
+
+/// Readable names of permissions for error messages, e.g. `Manage Server`
+fn permission_names(permissions: Permissions) -> String {
+    const NAMES: [(Permissions, &str); 8] = [
+        (Permissions::ADMINISTRATOR, "Administrator"),
+        (Permissions::MANAGE_GUILD, "Manage Server"),
+        (Permissions::MANAGE_ROLES, "Manage Roles"),
+        (Permissions::MANAGE_CHANNELS, "Manage Channels"),
+        (Permissions::MANAGE_NICKNAMES, "Manage Nicknames"),
+        (Permissions::MANAGE_MESSAGES, "Manage Messages"),
+        (Permissions::KICK_MEMBERS, "Kick Members"),
+        (Permissions::BAN_MEMBERS, "Ban Members"),
+    ];
+
+    let names: Vec<&str> = NAMES
+        .iter()
+        .filter(|(permission, _)| permissions.contains(*permission))
+        .map(|(_, name)| *name)
+        .collect();
+
+    if names.is_empty() {
+        format!("{:?}", permissions)
+    } else {
+        names.join(", ")
+    }
+}
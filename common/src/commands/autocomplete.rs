@@ -0,0 +1,99 @@
+use super::AutocompleteChoice;
+use super::definition::{MAX_CHOICE_NAME_LENGTH, MAX_CHOICE_VALUE_LENGTH, MAX_CHOICES};
+use tracing::warn;
+use twilight_model::application::command::{CommandOptionChoiceValue, CommandOptionType};
+use twilight_model::application::interaction::application_command::{
+    CommandDataOption, CommandOptionValue,
+};
+
+/// The option a member is typing in, with what they typed so far
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FocusedOption {
+    pub name: String,
+    /// The partial input, Discord sends it as text even for number options
+    pub value: String,
+    pub kind: CommandOptionType,
+}
+
+impl FocusedOption {
+    /// Find the focused option, looking inside subcommands and subcommand groups
+    pub fn find(options: &[CommandDataOption]) -> Option<FocusedOption> {
+        options.iter().find_map(|option| match &option.value {
+            CommandOptionValue::Focused(value, kind) => Some(FocusedOption {
+                name: option.name.clone(),
+                value: value.clone(),
+                kind: *kind,
+            }),
+            CommandOptionValue::SubCommand(options)
+            | CommandOptionValue::SubCommandGroup(options) => FocusedOption::find(options),
+            _ => None,
+        })
+    }
+}
+
+/// Drop choices Discord would reject and keep at most `MAX_CHOICES`
+///
+/// Discord fails the whole response if a single choice is invalid, so a bad choice is logged
+/// and left out instead.
+pub fn valid_choices(
+    choices: Vec<AutocompleteChoice>,
+    focused: &FocusedOption,
+) -> Vec<AutocompleteChoice> {
+    let total = choices.len();
+
+    let choices: Vec<_> = choices
+        .into_iter()
+        .filter(|choice| match check_choice(choice, focused.kind) {
+            Ok(()) => true,
+            Err(reason) => {
+                warn!(
+                    "Dropping autocomplete choice {:?} for {}: {}",
+                    choice.name.default, focused.name, reason
+                );
+                false
+            }
+        })
+        .take(MAX_CHOICES)
+        .collect();
+
+    if total > MAX_CHOICES {
+        warn!(
+            "Autocomplete for {} returned {} choices, only sending {}",
+            focused.name, total, MAX_CHOICES
+        );
+    }
+
+    choices
+}
+
+fn check_choice(choice: &AutocompleteChoice, kind: CommandOptionType) -> Result<(), String> {
+    let names = std::iter::once(&choice.name.default).chain(choice.name.localizations.values());
+    for name in names {
+        let length = name.chars().count();
+        if length == 0 || length > MAX_CHOICE_NAME_LENGTH {
+            return Err(format!(
+                "name must be 1-{} characters, got {}",
+                MAX_CHOICE_NAME_LENGTH, length
+            ));
+        }
+    }
+
+    match (&choice.value, kind) {
+        (CommandOptionChoiceValue::String(value), CommandOptionType::String) => {
+            if value.chars().count() > MAX_CHOICE_VALUE_LENGTH {
+                return Err(format!(
+                    "value must be at most {} characters",
+                    MAX_CHOICE_VALUE_LENGTH
+                ));
+            }
+            Ok(())
+        }
+        (CommandOptionChoiceValue::Integer(_), CommandOptionType::Integer)
+        | (CommandOptionChoiceValue::Integer(_), CommandOptionType::Number)
+        | (CommandOptionChoiceValue::Number(_), CommandOptionType::Number) => Ok(()),
+        (value, kind) => Err(format!(
+            "{:?} is not a valid value for a {:?} option",
+            value, kind
+        )),
+    }
+}
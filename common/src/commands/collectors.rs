@@ -0,0 +1,217 @@
+use super::components::CustomId;
+use super::{CommandContext, CommandError};
+use std::time::Duration;
+use tracing::warn;
+use twilight_model::application::interaction::message_component::MessageComponentInteractionData;
+use twilight_model::application::interaction::{Interaction, InteractionData};
+use twilight_model::channel::message::Component;
+use twilight_model::channel::message::component::{ActionRow, Button, ButtonStyle};
+use twilight_model::id::Id;
+use twilight_model::id::marker::MessageMarker;
+use twilight_util::builder::message::{ActionRowBuilder, ButtonBuilder};
+
+/// Custom id prefix of the confirm dialog buttons, they are collected and never routed
+const CONFIRM_PREFIX: &str = "confirm";
+
+/// A component used on a message while a command waited for it
+pub struct ComponentPress {
+    /// Context of the component interaction, it still needs a response
+    pub context: CommandContext,
+    pub data: MessageComponentInteractionData,
+}
+
+impl CommandContext {
+    /// Wait for a component on the response to this interaction
+    ///
+    /// Returns `None` when nothing passed `filter` before `timeout`, the components on the
+    /// response are disabled then so nobody presses a button that no longer does anything.
+    pub async fn await_component<F>(
+        &self,
+        timeout: Duration,
+        filter: F,
+    ) -> Result<Option<ComponentPress>, CommandError>
+    where
+        F: Fn(&Interaction) -> bool + Send + Sync + 'static,
+    {
+        let response = self.get_response().await?;
+
+        let press = self.collect_component(response.id, timeout, filter).await?;
+        if press.is_none() {
+            let components = disable_components(response.components);
+            if let Err(error) = self
+                .update(None, None, None, Some(&components), &[], &[])
+                .await
+            {
+                warn!("Could not disable components after timeout: {}", error);
+            }
+        }
+
+        Ok(press)
+    }
+
+    /// Wait for a component on a message, `None` after `timeout`
+    pub(super) async fn collect_component<F>(
+        &self,
+        message_id: Id<MessageMarker>,
+        timeout: Duration,
+        filter: F,
+    ) -> Result<Option<ComponentPress>, CommandError>
+    where
+        F: Fn(&Interaction) -> bool + Send + Sync + 'static,
+    {
+        let wait = self.context.standby.wait_for_component(message_id, filter);
+
+        match tokio::time::timeout(timeout, wait).await {
+            Ok(Ok(interaction)) => {
+                let Some(InteractionData::MessageComponent(data)) = interaction.data.clone() else {
+                    return Err(CommandError::Internal(
+                        "Collected a component interaction without component data".into(),
+                    ));
+                };
+
+                Ok(Some(ComponentPress {
+                    context: CommandContext::new(self.context.clone(), interaction),
+                    data: *data,
+                }))
+            }
+            Ok(Err(_canceled)) => Err(CommandError::Internal(
+                "Stopped waiting for a component".into(),
+            )),
+            Err(_elapsed) => Ok(None),
+        }
+    }
+}
+
+/// Components with every button and select menu disabled
+pub fn disable_components(components: Vec<Component>) -> Vec<Component> {
+    components
+        .into_iter()
+        .map(|component| match component {
+            Component::ActionRow(row) => Component::ActionRow(ActionRow {
+                components: disable_components(row.components),
+                ..row
+            }),
+            Component::Button(button) => Component::Button(Button {
+                disabled: true,
+                ..button
+            }),
+            Component::SelectMenu(mut menu) => {
+                menu.disabled = true;
+                Component::SelectMenu(menu)
+            }
+            other => other,
+        })
+        .collect()
+}
+
+/// Asks the invoking user to confirm an action with two buttons
+///
+/// ```ignore
+/// if !ConfirmDialog::new("Really unlink?").confirm_label("Unlink").ask(context).await? {
+///     return Ok(());
+/// }
+/// ```
+pub struct ConfirmDialog {
+    prompt: String,
+    confirm_label: String,
+    cancel_label: String,
+    cancelled: String,
+    timed_out: String,
+    timeout: Duration,
+}
+
+impl ConfirmDialog {
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            confirm_label: "Confirm".to_string(),
+            cancel_label: "Cancel".to_string(),
+            cancelled: "Cancelled".to_string(),
+            timed_out: "No answer, nothing was changed".to_string(),
+            timeout: Duration::from_secs(60),
+        }
+    }
+
+    pub fn confirm_label(mut self, label: impl Into<String>) -> Self {
+        self.confirm_label = label.into();
+        self
+    }
+
+    pub fn cancel_label(mut self, label: impl Into<String>) -> Self {
+        self.cancel_label = label.into();
+        self
+    }
+
+    /// Messages shown when the user cancels or doesn't answer in time
+    pub fn outcomes(mut self, cancelled: impl Into<String>, timed_out: impl Into<String>) -> Self {
+        self.cancelled = cancelled.into();
+        self.timed_out = timed_out.into();
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Show the dialog as the ephemeral response and wait for an answer, `true` if confirmed
+    ///
+    /// The dialog is the interaction's response, so a later reply on `context` replaces it.
+    pub async fn ask(self, context: &mut CommandContext) -> Result<bool, CommandError> {
+        let Some(user_id) = context.get_user_id() else {
+            return Err(CommandError::Internal("Interaction without a user".into()));
+        };
+
+        let custom_id = |answer: &str| {
+            CustomId::new(CONFIRM_PREFIX)
+                .arg(context.interaction.id)
+                .arg(answer)
+                .to_string()
+        };
+        let row = Component::ActionRow(
+            ActionRowBuilder::new()
+                .component(
+                    ButtonBuilder::new(ButtonStyle::Danger)
+                        .label(self.confirm_label)
+                        .custom_id(custom_id("yes"))
+                        .build(),
+                )
+                .component(
+                    ButtonBuilder::new(ButtonStyle::Secondary)
+                        .label(self.cancel_label)
+                        .custom_id(custom_id("no"))
+                        .build(),
+                )
+                .build(),
+        );
+
+        context
+            .reply_components(self.prompt.clone(), vec![row], true)
+            .await?;
+
+        let press = context
+            .await_component(self.timeout, move |interaction: &Interaction| {
+                interaction.author_id() == Some(user_id)
+            })
+            .await?;
+
+        let Some(mut press) = press else {
+            context
+                .update(Some(&self.timed_out), None, None, Some(&[]), &[], &[])
+                .await?;
+            return Ok(false);
+        };
+
+        let confirmed = CustomId::parse(&press.data.custom_id).get::<String>(1)? == "yes";
+
+        // Take the buttons away right away, the caller replies with the outcome afterwards
+        let content = if confirmed {
+            self.prompt
+        } else {
+            self.cancelled
+        };
+        press.context.update_message(content, Vec::new()).await?;
+
+        Ok(confirmed)
+    }
+}
@@ -0,0 +1,151 @@
+use super::access::CommandAccess;
+use super::cooldowns::Cooldown;
+use super::{CommandContext, CommandError};
+use async_trait::async_trait;
+use std::fmt::Display;
+use std::str::FromStr;
+use twilight_model::application::interaction::message_component::MessageComponentInteractionData;
+use twilight_model::application::interaction::modal::ModalInteractionData;
+
+/// Separates the prefix and the arguments of a custom id
+const SEPARATOR: char = ':';
+/// Longest custom id Discord accepts
+pub const MAX_CUSTOM_ID_LENGTH: usize = 100;
+
+/// A component or modal custom id in the form `prefix:arg:arg`
+///
+/// The prefix picks the handler, the arguments carry the state the handler needs later on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomId {
+    prefix: String,
+    args: Vec<String>,
+}
+
+impl CustomId {
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            args: Vec::new(),
+        }
+    }
+
+    pub fn arg(mut self, arg: impl Display) -> Self {
+        self.args.push(arg.to_string());
+        self
+    }
+
+    pub fn parse(custom_id: &str) -> Self {
+        let mut parts = custom_id.split(SEPARATOR);
+
+        Self {
+            prefix: parts.next().unwrap_or_default().to_string(),
+            args: parts.map(unescape).collect(),
+        }
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Parse the argument at `index`
+    pub fn get<T: FromStr>(&self, index: usize) -> Result<T, CommandError> {
+        self.args
+            .get(index)
+            .and_then(|arg| arg.parse().ok())
+            .ok_or_else(|| {
+                CommandError::Validation(format!(
+                    "Invalid argument {} in custom id `{}`",
+                    index, self
+                ))
+            })
+    }
+
+    /// The encoded custom id, or an error when it is too long for Discord
+    pub fn build(&self) -> Result<String, CommandError> {
+        let custom_id = self.to_string();
+
+        if custom_id.len() > MAX_CUSTOM_ID_LENGTH {
+            return Err(CommandError::Internal(format!(
+                "Custom id `{}` is longer than {} characters",
+                custom_id, MAX_CUSTOM_ID_LENGTH
+            )));
+        }
+
+        Ok(custom_id)
+    }
+}
+
+impl Display for CustomId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.prefix)?;
+        for arg in &self.args {
+            write!(f, "{}{}", SEPARATOR, escape(arg))?;
+        }
+        Ok(())
+    }
+}
+
+fn escape(arg: &str) -> String {
+    arg.replace('%', "%25").replace(SEPARATOR, "%3A")
+}
+
+fn unescape(arg: &str) -> String {
+    arg.replace("%3A", ":").replace("%25", "%")
+}
+
+/// Handles button and select menu interactions whose custom id starts with `prefix`
+#[async_trait]
+pub trait ComponentHandler: Send + Sync {
+    /// The custom id prefix this handler owns
+    fn prefix(&self) -> &str;
+
+    async fn handle(
+        &self,
+        context: &mut CommandContext,
+        custom_id: &CustomId,
+        data: &MessageComponentInteractionData,
+    ) -> Result<(), CommandError>;
+
+    /// Who may use the component and where, checked by the registry before `handle`
+    fn access(&self) -> CommandAccess {
+        CommandAccess::default()
+    }
+
+    /// How often the component can be used, `None` for no limit
+    fn cooldown(&self) -> Option<Cooldown> {
+        None
+    }
+}
+
+/// Handles submitted modals whose custom id starts with `prefix`
+#[async_trait]
+pub trait ModalHandler: Send + Sync {
+    /// The custom id prefix this handler owns
+    fn prefix(&self) -> &str;
+
+    async fn submit(
+        &self,
+        context: &mut CommandContext,
+        custom_id: &CustomId,
+        data: &ModalInteractionData,
+    ) -> Result<(), CommandError>;
+
+    /// Who may submit the modal and where, checked by the registry before `submit`
+    fn access(&self) -> CommandAccess {
+        CommandAccess::default()
+    }
+
+    /// How often the modal can be submitted, `None` for no limit
+    fn cooldown(&self) -> Option<Cooldown> {
+        None
+    }
+}
+
+/// Get the value of a text input in a submitted modal
+pub fn modal_value<'a>(data: &'a ModalInteractionData, custom_id: &str) -> Option<&'a str> {
+    data.components
+        .iter()
+        .flat_map(|row| &row.components)
+        .find(|component| component.custom_id == custom_id)
+        .and_then(|component| component.value.as_deref())
+}
@@ -0,0 +1,366 @@
+use super::middleware::{Middleware, Next, Target};
+use super::{CommandContext, CommandError, Locale, LocalizedText};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, GuildMarker, UserMarker};
+
+/// How often buckets that filled up again are dropped
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Who shares a cooldown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CooldownBucket {
+    /// Every member on their own
+    User,
+    /// Everyone in a guild, members in DMs are limited on their own
+    Guild,
+    /// Everyone in a channel
+    Channel,
+    /// Everyone everywhere
+    Global,
+}
+
+/// How often a command, component or modal can be used
+///
+/// Uses come from a bucket of `uses` tokens that refills over `per`, so short bursts are fine
+/// while a steady stream is slowed down to `uses` per `per`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cooldown {
+    pub bucket: CooldownBucket,
+    pub uses: u32,
+    pub per: Duration,
+}
+
+impl Cooldown {
+    /// Panics when `per` is zero, the bucket could never refill
+    pub fn new(bucket: CooldownBucket, uses: u32, per: Duration) -> Self {
+        assert!(!per.is_zero(), "a cooldown needs a non-zero period");
+
+        Self { bucket, uses, per }
+    }
+
+    /// Tokens added back per second
+    fn rate(&self) -> f64 {
+        self.uses as f64 / self.per.as_secs_f64()
+    }
+}
+
+/// Who a use counts against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum BucketKey {
+    User(Id<UserMarker>),
+    Guild(Id<GuildMarker>),
+    Channel(Id<ChannelMarker>),
+    Global,
+}
+
+impl BucketKey {
+    /// Members in DMs have no guild or channel to share, they are limited on their own
+    fn new(
+        bucket: CooldownBucket,
+        user_id: Id<UserMarker>,
+        guild_id: Option<Id<GuildMarker>>,
+        channel_id: Option<Id<ChannelMarker>>,
+    ) -> Self {
+        match bucket {
+            CooldownBucket::User => BucketKey::User(user_id),
+            CooldownBucket::Guild => guild_id.map_or(BucketKey::User(user_id), BucketKey::Guild),
+            CooldownBucket::Channel => {
+                channel_id.map_or(BucketKey::User(user_id), BucketKey::Channel)
+            }
+            CooldownBucket::Global => BucketKey::Global,
+        }
+    }
+}
+
+/// Whole seconds to tell a limited member, never less than one
+fn wait_seconds(wait: Duration) -> u64 {
+    (wait.as_secs_f64().ceil() as u64).max(1)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// When the bucket is full again and can be forgotten
+    full_at: Instant,
+}
+
+/// Enforces the cooldowns targets declare, see `CommandBundle::cooldown`
+pub struct CooldownLayer {
+    buckets: Mutex<HashMap<(&'static str, String, BucketKey), Bucket>>,
+    last_prune: Mutex<Instant>,
+    /// Reply to limited members, `{seconds}` is replaced with the wait
+    message: LocalizedText,
+}
+
+impl CooldownLayer {
+    pub fn new() -> Self {
+        Self {
+            buckets: Mutex::new(HashMap::new()),
+            last_prune: Mutex::new(Instant::now()),
+            message: LocalizedText::new("Slow down! Try again in {seconds}s.")
+                .with_localization(
+                    Locale::German,
+                    "Nicht so schnell! Versuche es in {seconds}s erneut.",
+                )
+                .with_localization(
+                    Locale::Spanish,
+                    "¡Más despacio! Inténtalo de nuevo en {seconds}s.",
+                )
+                .with_localization(Locale::French, "Doucement ! Réessaie dans {seconds}s."),
+        }
+    }
+
+    /// Take a use from the bucket, or how long until one is available
+    fn take(
+        &self,
+        target: (&'static str, String, BucketKey),
+        cooldown: &Cooldown,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        self.prune(now);
+
+        let capacity = cooldown.uses as f64;
+        let rate = cooldown.rate();
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(target).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+            full_at: now,
+        });
+
+        let refilled = (now - bucket.updated).as_secs_f64() * rate;
+        bucket.tokens = (bucket.tokens + refilled).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens < 1.0 {
+            return Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate));
+        }
+
+        bucket.tokens -= 1.0;
+        bucket.full_at = now + Duration::from_secs_f64((capacity - bucket.tokens) / rate);
+        Ok(())
+    }
+
+    /// Forget buckets that are full again, they behave the same as a new one
+    fn prune(&self, now: Instant) {
+        let mut last_prune = self.last_prune.lock().unwrap();
+        if now - *last_prune < PRUNE_INTERVAL {
+            return;
+        }
+        *last_prune = now;
+
+        self.buckets
+            .lock()
+            .unwrap()
+            .retain(|_, bucket| bucket.full_at > now);
+    }
+}
+
+impl Default for CooldownLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Middleware for CooldownLayer {
+    async fn handle(
+        &self,
+        context: &mut CommandContext,
+        target: &Target<'_>,
+        next: Next<'_>,
+    ) -> Result<(), CommandError> {
+        let Some(cooldown) = target
+            .cooldown()
+            .filter(|cooldown| cooldown.uses > 0 && !cooldown.per.is_zero())
+        else {
+            return next.run(context).await;
+        };
+        let Some(user_id) = context.get_user_id() else {
+            return next.run(context).await;
+        };
+
+        let key = BucketKey::new(
+            cooldown.bucket,
+            user_id,
+            context.get_guild_id(),
+            context.get_channel_id(),
+        );
+        let target = (target.kind(), target.name().to_string(), key);
+
+        match self.take(target, &cooldown, Instant::now()) {
+            Ok(()) => next.run(context).await,
+            Err(wait) => {
+                let locale = context.get_effective_locale();
+                let seconds = wait_seconds(wait).to_string();
+                let message = self
+                    .message
+                    .get(locale.as_ref())
+                    .replace("{seconds}", &seconds);
+
+                context.reply_ephemeral(message).await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(key: BucketKey) -> (&'static str, String, BucketKey) {
+        ("command", "verify".to_string(), key)
+    }
+
+    #[test]
+    fn allows_a_burst_of_uses() {
+        let layer = CooldownLayer::new();
+        let cooldown = Cooldown::new(CooldownBucket::User, 4, Duration::from_secs(8));
+        let key = BucketKey::User(Id::new(1));
+        let now = Instant::now();
+
+        for _ in 0..4 {
+            assert_eq!(layer.take(target(key), &cooldown, now), Ok(()));
+        }
+
+        let wait = layer
+            .take(target(key), &cooldown, now)
+            .expect_err("a fifth use should be limited");
+        assert_eq!(wait, Duration::from_secs(2));
+    }
+
+    #[test]
+    fn refills_over_time() {
+        let layer = CooldownLayer::new();
+        let cooldown = Cooldown::new(CooldownBucket::User, 2, Duration::from_secs(4));
+        let key = BucketKey::User(Id::new(1));
+        let now = Instant::now();
+
+        assert_eq!(layer.take(target(key), &cooldown, now), Ok(()));
+        assert_eq!(layer.take(target(key), &cooldown, now), Ok(()));
+
+        // One use comes back every two seconds
+        let later = now + Duration::from_secs(1);
+        let wait = layer
+            .take(target(key), &cooldown, later)
+            .expect_err("no use should be back yet");
+        assert_eq!(wait, Duration::from_secs(1));
+
+        let later = now + Duration::from_secs(2);
+        assert_eq!(layer.take(target(key), &cooldown, later), Ok(()));
+        assert!(layer.take(target(key), &cooldown, later).is_err());
+
+        let later = now + Duration::from_secs(60);
+        assert_eq!(layer.take(target(key), &cooldown, later), Ok(()));
+        assert_eq!(layer.take(target(key), &cooldown, later), Ok(()));
+    }
+
+    #[test]
+    fn buckets_are_separate() {
+        let layer = CooldownLayer::new();
+        let cooldown = Cooldown::new(CooldownBucket::User, 1, Duration::from_secs(10));
+        let now = Instant::now();
+
+        assert_eq!(
+            layer.take(target(BucketKey::User(Id::new(1))), &cooldown, now),
+            Ok(())
+        );
+        assert_eq!(
+            layer.take(target(BucketKey::User(Id::new(2))), &cooldown, now),
+            Ok(())
+        );
+        assert_eq!(
+            layer.take(
+                ("modal", "verify".to_string(), BucketKey::User(Id::new(1))),
+                &cooldown,
+                now
+            ),
+            Ok(())
+        );
+        assert!(
+            layer
+                .take(target(BucketKey::User(Id::new(1))), &cooldown, now)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn prunes_full_buckets() {
+        let layer = CooldownLayer::new();
+        let cooldown = Cooldown::new(CooldownBucket::User, 1, Duration::from_secs(10));
+        let now = Instant::now();
+
+        assert_eq!(
+            layer.take(target(BucketKey::User(Id::new(1))), &cooldown, now),
+            Ok(())
+        );
+        assert_eq!(layer.buckets.lock().unwrap().len(), 1);
+
+        let later = now + PRUNE_INTERVAL;
+        assert_eq!(
+            layer.take(target(BucketKey::User(Id::new(2))), &cooldown, later),
+            Ok(())
+        );
+        let buckets = layer.buckets.lock().unwrap();
+        assert_eq!(buckets.len(), 1);
+        assert!(buckets.contains_key(&target(BucketKey::User(Id::new(2)))));
+    }
+
+    #[test]
+    fn waits_are_rounded_up() {
+        assert_eq!(wait_seconds(Duration::ZERO), 1);
+        assert_eq!(wait_seconds(Duration::from_millis(10)), 1);
+        assert_eq!(wait_seconds(Duration::from_secs(2)), 2);
+        assert_eq!(wait_seconds(Duration::from_millis(2001)), 3);
+    }
+
+    #[test]
+    fn guild_and_channel_buckets_fall_back_to_the_user_in_dms() {
+        let user_id = Id::new(1);
+        let guild_id = Id::new(2);
+        let channel_id = Id::new(3);
+
+        assert_eq!(
+            BucketKey::new(CooldownBucket::Guild, user_id, None, Some(channel_id)),
+            BucketKey::User(user_id)
+        );
+        assert_eq!(
+            BucketKey::new(
+                CooldownBucket::Guild,
+                user_id,
+                Some(guild_id),
+                Some(channel_id)
+            ),
+            BucketKey::Guild(guild_id)
+        );
+        assert_eq!(
+            BucketKey::new(CooldownBucket::Channel, user_id, None, None),
+            BucketKey::User(user_id)
+        );
+        assert_eq!(
+            BucketKey::new(
+                CooldownBucket::Channel,
+                user_id,
+                Some(guild_id),
+                Some(channel_id)
+            ),
+            BucketKey::Channel(channel_id)
+        );
+        assert_eq!(
+            BucketKey::new(CooldownBucket::Global, user_id, None, None),
+            BucketKey::Global
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "non-zero period")]
+    fn rejects_a_zero_period() {
+        Cooldown::new(CooldownBucket::User, 1, Duration::ZERO);
+    }
+}
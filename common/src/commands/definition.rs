@@ -0,0 +1,593 @@
+use super::options::ChoiceValue;
+use super::{CommandBundle, Locale, LocalizedText};
+use std::collections::{HashMap, HashSet};
+use twilight_model::application::command::{
+    Command as ApplicationCommand, CommandOption, CommandOptionChoice, CommandOptionChoiceValue,
+    CommandOptionType, CommandOptionValue, CommandType,
+};
+use twilight_model::channel::ChannelType;
+use twilight_model::guild::Permissions;
+use twilight_util::builder::command::CommandBuilder;
+
+/// Longest command and option name Discord accepts
+const MAX_NAME_LENGTH: usize = 32;
+/// Longest command and option description Discord accepts
+pub(crate) const MAX_DESCRIPTION_LENGTH: usize = 100;
+/// Longest choice name Discord accepts
+pub(crate) const MAX_CHOICE_NAME_LENGTH: usize = 100;
+/// Longest string choice value Discord accepts
+pub(crate) const MAX_CHOICE_VALUE_LENGTH: usize = 100;
+/// Most options a command, subcommand or group can have
+const MAX_OPTIONS: usize = 25;
+/// Most choices an option can have
+pub(crate) const MAX_CHOICES: usize = 25;
+/// Highest `min_length` and `max_length` of a string option
+const MAX_STRING_LENGTH: u16 = 6000;
+/// Most characters of all names, descriptions and choices of a command combined, per locale
+const MAX_COMMAND_LENGTH: usize = 4000;
+/// Most chat input commands per application, globally and in each guild
+pub const MAX_CHAT_INPUT_COMMANDS: usize = 100;
+/// Most user or message commands per application, globally and in each guild
+pub const MAX_CONTEXT_MENU_COMMANDS: usize = 5;
+
+/// Builds a command definition where every name and description is a `LocalizedText`
+pub struct LocalizedCommandBuilder {
+    command: ApplicationCommand,
+}
+
+impl LocalizedCommandBuilder {
+    pub fn new(name: LocalizedText, description: LocalizedText, kind: CommandType) -> Self {
+        let mut command = CommandBuilder::new(&name.default, &description.default, kind).build();
+        command.name_localizations = name.to_discord_localizations();
+        command.description_localizations = description.to_discord_localizations();
+
+        Self { command }
+    }
+
+    pub fn option(mut self, option: LocalizedOptionBuilder) -> Self {
+        self.command.options.push(option.build());
+        self
+    }
+
+    pub fn default_member_permissions(mut self, permissions: Permissions) -> Self {
+        self.command.default_member_permissions = Some(permissions);
+        self
+    }
+
+    pub fn build(self) -> ApplicationCommand {
+        self.command
+    }
+}
+
+/// Builds a command option where every name, description and choice is a `LocalizedText`
+pub struct LocalizedOptionBuilder {
+    option: CommandOption,
+}
+
+impl LocalizedOptionBuilder {
+    pub fn new(kind: CommandOptionType, name: LocalizedText, description: LocalizedText) -> Self {
+        Self {
+            option: CommandOption {
+                autocomplete: None,
+                channel_types: None,
+                choices: None,
+                description: description.default.clone(),
+                description_localizations: description.to_discord_localizations(),
+                kind,
+                max_length: None,
+                max_value: None,
+                min_length: None,
+                min_value: None,
+                name: name.default.clone(),
+                name_localizations: name.to_discord_localizations(),
+                options: None,
+                required: None,
+            },
+        }
+    }
+
+    pub fn required(mut self, required: bool) -> Self {
+        self.option.required = Some(required);
+        self
+    }
+
+    pub fn autocomplete(mut self, autocomplete: bool) -> Self {
+        self.option.autocomplete = Some(autocomplete);
+        self
+    }
+
+    pub fn string_choice(self, name: LocalizedText, value: impl Into<String>) -> Self {
+        self.choice(name, CommandOptionChoiceValue::String(value.into()))
+    }
+
+    pub fn integer_choice(self, name: LocalizedText, value: i64) -> Self {
+        self.choice(name, CommandOptionChoiceValue::Integer(value))
+    }
+
+    pub fn choice(mut self, name: LocalizedText, value: CommandOptionChoiceValue) -> Self {
+        self.option
+            .choices
+            .get_or_insert_with(Vec::new)
+            .push(CommandOptionChoice {
+                name: name.default.clone(),
+                name_localizations: name.to_discord_localizations(),
+                value,
+            });
+        self
+    }
+
+    pub fn choices<L: Into<String>, V: ChoiceValue>(
+        self,
+        choices: impl IntoIterator<Item = (L, V)>,
+    ) -> Self {
+        choices.into_iter().fold(self, |option, (name, value)| {
+            option.choice(LocalizedText::new(name), value.into_choice())
+        })
+    }
+
+    /// Lowest value of an integer or number option, see `min_number` for fractional bounds
+    pub fn min_value(mut self, value: i64) -> Self {
+        self.option.min_value = Some(self.bound(value));
+        self
+    }
+
+    /// Highest value of an integer or number option, see `max_number` for fractional bounds
+    pub fn max_value(mut self, value: i64) -> Self {
+        self.option.max_value = Some(self.bound(value));
+        self
+    }
+
+    /// Lowest value of a number option
+    pub fn min_number(mut self, value: f64) -> Self {
+        self.option.min_value = Some(CommandOptionValue::Number(value));
+        self
+    }
+
+    /// Highest value of a number option
+    pub fn max_number(mut self, value: f64) -> Self {
+        self.option.max_value = Some(CommandOptionValue::Number(value));
+        self
+    }
+
+    /// A whole bound in the type of the option
+    fn bound(&self, value: i64) -> CommandOptionValue {
+        match self.option.kind {
+            CommandOptionType::Number => CommandOptionValue::Number(value as f64),
+            _ => CommandOptionValue::Integer(value),
+        }
+    }
+
+    pub fn channel_types(mut self, channel_types: impl IntoIterator<Item = ChannelType>) -> Self {
+        self.option.channel_types = Some(channel_types.into_iter().collect());
+        self
+    }
+
+    /// Nested option of a subcommand or subcommand group
+    pub fn option(mut self, option: LocalizedOptionBuilder) -> Self {
+        self.option
+            .options
+            .get_or_insert_with(Vec::new)
+            .push(option.build());
+        self
+    }
+
+    pub fn build(self) -> CommandOption {
+        self.option
+    }
+}
+
+/// A command definition Discord would reject
+#[derive(Debug)]
+pub struct CommandDefinitionError {
+    pub command: String,
+    /// Option or choice path inside the command, empty for the command itself
+    pub path: String,
+    /// Locale of the offending localization, `None` for the default text
+    pub locale: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for CommandDefinitionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "/{}", self.command)?;
+        if !self.path.is_empty() {
+            write!(f, " {}", self.path)?;
+        }
+        if let Some(locale) = &self.locale {
+            write!(f, " ({})", locale)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for CommandDefinitionError {}
+
+/// The definition sent to Discord, with the command's access settings applied
+pub fn deployed_definition(command: &dyn CommandBundle) -> ApplicationCommand {
+    let mut definition = command.definition();
+    command.access().apply(&mut definition);
+    definition
+}
+
+/// Check a command against Discord's documented limits, for the default text and every locale
+///
+/// Covers names, lengths, option and choice counts, option order and nesting, so a bad
+/// definition is caught on startup instead of failing the deploy with an HTTP 400.
+pub fn validate_command(command: &ApplicationCommand) -> Result<(), CommandDefinitionError> {
+    let validator = Validator {
+        command: &command.name,
+    };
+    let chat_input = command.kind == CommandType::ChatInput;
+
+    validator.text(
+        "",
+        &command.name,
+        command.name_localizations.as_ref(),
+        |name| validate_name(name, chat_input),
+    )?;
+    if chat_input {
+        validator.text(
+            "",
+            &command.description,
+            command.description_localizations.as_ref(),
+            |description| validate_length(description, MAX_DESCRIPTION_LENGTH),
+        )?;
+    } else {
+        // User and message commands show up in menus without a description or options
+        if !command.description.is_empty() {
+            return Err(validator.error("", None, "must not have a description".to_string()));
+        }
+        if !command.options.is_empty() {
+            return Err(validator.error("", None, "must not have options".to_string()));
+        }
+    }
+
+    validator.options("", None, &command.options)?;
+
+    // Discord counts the text of every locale separately
+    let locales =
+        std::iter::once(None).chain(Locale::ALL.iter().map(|locale| Some(locale.as_str())));
+    for locale in locales {
+        let length = combined_length(command, locale);
+        if length > MAX_COMMAND_LENGTH {
+            return Err(validator.error(
+                "",
+                locale,
+                format!(
+                    "names, descriptions and choices are {} characters, at most {} are allowed",
+                    length, MAX_COMMAND_LENGTH
+                ),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// The text of a name or description in a locale, or the default text
+fn localized<'a>(
+    default: &'a str,
+    localizations: Option<&'a HashMap<String, String>>,
+    locale: Option<&str>,
+) -> &'a str {
+    locale
+        .and_then(|locale| localizations?.get(locale))
+        .map_or(default, String::as_str)
+}
+
+fn combined_length(command: &ApplicationCommand, locale: Option<&str>) -> usize {
+    fn option_length(option: &CommandOption, locale: Option<&str>) -> usize {
+        let text = localized(&option.name, option.name_localizations.as_ref(), locale)
+            .chars()
+            .count()
+            + localized(
+                &option.description,
+                option.description_localizations.as_ref(),
+                locale,
+            )
+            .chars()
+            .count();
+
+        let choices: usize = option
+            .choices
+            .iter()
+            .flatten()
+            .map(|choice| {
+                let value = match &choice.value {
+                    CommandOptionChoiceValue::String(value) => value.chars().count(),
+                    _ => 0,
+                };
+                localized(&choice.name, choice.name_localizations.as_ref(), locale)
+                    .chars()
+                    .count()
+                    + value
+            })
+            .sum();
+
+        let options: usize = option
+            .options
+            .iter()
+            .flatten()
+            .map(|option| option_length(option, locale))
+            .sum();
+
+        text + choices + options
+    }
+
+    localized(&command.name, command.name_localizations.as_ref(), locale)
+        .chars()
+        .count()
+        + localized(
+            &command.description,
+            command.description_localizations.as_ref(),
+            locale,
+        )
+        .chars()
+        .count()
+        + command
+            .options
+            .iter()
+            .map(|option| option_length(option, locale))
+            .sum::<usize>()
+}
+
+struct Validator<'a> {
+    command: &'a str,
+}
+
+impl Validator<'_> {
+    fn error(&self, path: &str, locale: Option<&str>, message: String) -> CommandDefinitionError {
+        CommandDefinitionError {
+            command: self.command.to_string(),
+            path: path.to_string(),
+            locale: locale.map(str::to_string),
+            message,
+        }
+    }
+
+    /// Checks the default text and each of its localizations
+    fn text(
+        &self,
+        path: &str,
+        default: &str,
+        localizations: Option<&HashMap<String, String>>,
+        check: impl Fn(&str) -> Result<(), String>,
+    ) -> Result<(), CommandDefinitionError> {
+        check(default).map_err(|message| self.error(path, None, message))?;
+
+        for (locale, text) in localizations.into_iter().flatten() {
+            if Locale::from_discord(locale).is_none() {
+                return Err(self.error(path, Some(locale), "unknown locale".to_string()));
+            }
+            check(text).map_err(|message| self.error(path, Some(locale), message))?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks the options of a command (no `parent`), subcommand or subcommand group
+    fn options(
+        &self,
+        path: &str,
+        parent: Option<CommandOptionType>,
+        options: &[CommandOption],
+    ) -> Result<(), CommandDefinitionError> {
+        if options.len() > MAX_OPTIONS {
+            return Err(self.error(
+                path,
+                None,
+                format!(
+                    "has {} options, at most {} are allowed",
+                    options.len(),
+                    MAX_OPTIONS
+                ),
+            ));
+        }
+
+        let is_subcommand = |option: &CommandOption| {
+            matches!(
+                option.kind,
+                CommandOptionType::SubCommand | CommandOptionType::SubCommandGroup
+            )
+        };
+        let subcommands = options
+            .iter()
+            .filter(|option| is_subcommand(option))
+            .count();
+
+        if subcommands > 0 && subcommands < options.len() {
+            return Err(self.error(
+                path,
+                None,
+                "can't mix subcommands with other options".to_string(),
+            ));
+        }
+        if parent == Some(CommandOptionType::SubCommandGroup)
+            && options
+                .iter()
+                .any(|option| option.kind != CommandOptionType::SubCommand)
+        {
+            return Err(self.error(
+                path,
+                None,
+                "subcommand groups can only contain subcommands".to_string(),
+            ));
+        }
+        if parent == Some(CommandOptionType::SubCommand) && subcommands > 0 {
+            return Err(self.error(
+                path,
+                None,
+                "subcommands can't contain subcommands".to_string(),
+            ));
+        }
+
+        let mut names = HashSet::new();
+        let mut seen_optional = false;
+
+        for option in options {
+            if !names.insert(option.name.as_str()) {
+                return Err(self.error(
+                    path,
+                    None,
+                    format!("has two options named `{}`", option.name),
+                ));
+            }
+
+            if option.required.unwrap_or(false) {
+                if seen_optional {
+                    return Err(self.error(
+                        path,
+                        None,
+                        format!(
+                            "required option `{}` must come before the optional ones",
+                            option.name
+                        ),
+                    ));
+                }
+            } else if !is_subcommand(option) {
+                seen_optional = true;
+            }
+
+            self.option(path, option)?;
+        }
+
+        Ok(())
+    }
+
+    fn option(&self, parent: &str, option: &CommandOption) -> Result<(), CommandDefinitionError> {
+        let path = if parent.is_empty() {
+            option.name.clone()
+        } else {
+            format!("{} {}", parent, option.name)
+        };
+
+        self.text(
+            &path,
+            &option.name,
+            option.name_localizations.as_ref(),
+            |name| validate_name(name, true),
+        )?;
+        self.text(
+            &path,
+            &option.description,
+            option.description_localizations.as_ref(),
+            |description| validate_length(description, MAX_DESCRIPTION_LENGTH),
+        )?;
+
+        let choices = option.choices.as_deref().unwrap_or_default();
+        if choices.len() > MAX_CHOICES {
+            return Err(self.error(
+                &path,
+                None,
+                format!(
+                    "has {} choices, at most {} are allowed",
+                    choices.len(),
+                    MAX_CHOICES
+                ),
+            ));
+        }
+        if !choices.is_empty() && option.autocomplete == Some(true) {
+            return Err(self.error(
+                &path,
+                None,
+                "can't have both choices and autocomplete".to_string(),
+            ));
+        }
+
+        for choice in choices {
+            let choice_path = format!("{} choice \"{}\"", path, choice.name);
+            self.text(
+                &choice_path,
+                &choice.name,
+                choice.name_localizations.as_ref(),
+                |name| validate_length(name, MAX_CHOICE_NAME_LENGTH),
+            )?;
+
+            if let CommandOptionChoiceValue::String(value) = &choice.value {
+                validate_length(value, MAX_CHOICE_VALUE_LENGTH)
+                    .map_err(|message| self.error(&choice_path, None, message))?;
+            }
+        }
+
+        self.limits(&path, option)?;
+
+        if let Some(options) = &option.options {
+            self.options(&path, Some(option.kind), options)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks the length and value limits of an option
+    fn limits(&self, path: &str, option: &CommandOption) -> Result<(), CommandDefinitionError> {
+        for length in [option.min_length, option.max_length].into_iter().flatten() {
+            if length > MAX_STRING_LENGTH {
+                return Err(self.error(
+                    path,
+                    None,
+                    format!(
+                        "length limits must be at most {}, got {}",
+                        MAX_STRING_LENGTH, length
+                    ),
+                ));
+            }
+        }
+        if let (Some(min), Some(max)) = (option.min_length, option.max_length) {
+            if min > max {
+                return Err(self.error(
+                    path,
+                    None,
+                    format!("min_length {} is above max_length {}", min, max),
+                ));
+            }
+        }
+
+        let as_number = |value: &CommandOptionValue| match value {
+            CommandOptionValue::Integer(value) => *value as f64,
+            CommandOptionValue::Number(value) => *value,
+        };
+        if let (Some(min), Some(max)) = (&option.min_value, &option.max_value) {
+            if as_number(min) > as_number(max) {
+                return Err(self.error(
+                    path,
+                    None,
+                    format!("min_value {:?} is above max_value {:?}", min, max),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_length(text: &str, max_length: usize) -> Result<(), String> {
+    let length = text.chars().count();
+    if length == 0 || length > max_length {
+        return Err(format!(
+            "must be 1 to {} characters, `{}` is {}",
+            max_length, text, length
+        ));
+    }
+
+    Ok(())
+}
+
+/// Chat input names must be lowercase letters, numbers, `-` and `_`, other command types may
+/// use any characters
+fn validate_name(name: &str, chat_input: bool) -> Result<(), String> {
+    validate_length(name, MAX_NAME_LENGTH)?;
+
+    if chat_input {
+        if let Some(invalid) = name
+            .chars()
+            .find(|c| !(c.is_alphanumeric() || *c == '-' || *c == '_'))
+        {
+            return Err(format!("`{}` contains `{}`", name, invalid));
+        }
+        if name.chars().any(char::is_uppercase) {
+            return Err(format!("`{}` must be lowercase", name));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,344 @@
+use super::definition::MAX_DESCRIPTION_LENGTH;
+use crate::context::Context;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use tracing::info;
+use twilight_http::request::Request;
+use twilight_http::routing::Route;
+use twilight_model::application::command::{Command, CommandType};
+use twilight_model::id::Id;
+use twilight_model::id::marker::{CommandMarker, GuildMarker};
+
+/// Put in front of the description of commands deployed to dev guilds
+pub const DEV_MARKER: &str = "[dev]";
+
+/// How `CommandRegistry::deploy` talks to Discord
+#[derive(Debug, Clone, Default)]
+pub struct DeployOptions {
+    /// Only log the changes that would be made
+    pub dry_run: bool,
+    /// Deploy global commands to these guilds instead, where updates show up right away
+    ///
+    /// Global commands on Discord are left alone while this is set.
+    pub dev_guilds: Vec<Id<GuildMarker>>,
+    /// Guilds that were dev guilds before, the dev copies of global commands are deleted there
+    ///
+    /// Other commands in these guilds are left alone, the caller keeps track of them between
+    /// deploys.
+    pub former_dev_guilds: Vec<Id<GuildMarker>>,
+}
+
+/// Copy of a global command for a dev guild, with `DEV_MARKER` in its descriptions
+///
+/// User and message commands have no description, so they are deployed unmarked.
+pub fn dev_definition(command: &Command) -> Command {
+    let mut command = command.clone();
+
+    if command.kind == CommandType::ChatInput {
+        let mark = |description: &mut String| {
+            *description = format!("{} {}", DEV_MARKER, description)
+                .chars()
+                .take(MAX_DESCRIPTION_LENGTH)
+                .collect();
+        };
+
+        mark(&mut command.description);
+        for description in command
+            .description_localizations
+            .iter_mut()
+            .flat_map(|map| map.values_mut())
+        {
+            mark(description);
+        }
+    }
+
+    command
+}
+
+/// Whether a command on Discord was deployed for development
+pub fn is_dev_definition(command: &Command) -> bool {
+    command.description.starts_with(DEV_MARKER)
+}
+
+/// Where a set of commands lives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeployTarget {
+    Global,
+    Guild(Id<GuildMarker>),
+}
+
+impl Display for DeployTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeployTarget::Global => write!(f, "global"),
+            DeployTarget::Guild(guild_id) => write!(f, "guild {}", guild_id),
+        }
+    }
+}
+
+/// One change needed to make Discord match the local definitions
+#[derive(Debug, Clone)]
+pub enum CommandChange {
+    Create(Command),
+    Update {
+        id: Id<CommandMarker>,
+        command: Command,
+        /// Names of the fields that differ, e.g. `description`
+        fields: Vec<String>,
+    },
+    Delete {
+        id: Id<CommandMarker>,
+        name: String,
+        /// A leftover from deploying to dev guilds
+        dev: bool,
+    },
+}
+
+impl Display for CommandChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandChange::Create(command) => write!(f, "+ /{}", command.name),
+            CommandChange::Update {
+                command, fields, ..
+            } => write!(f, "~ /{}: {}", command.name, fields.join(", ")),
+            CommandChange::Delete { name, dev, .. } => {
+                write!(f, "- /{}", name)?;
+                if *dev {
+                    write!(f, " (dev leftover)")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Compare local definitions with the commands Discord has and list what has to change
+///
+/// Commands are matched by name and type. Fields Discord fills in itself (ids, versions) and
+/// values equal to their default are ignored, so an unchanged command is never patched.
+pub fn plan(local: &[Command], remote: &[Command]) -> Vec<CommandChange> {
+    let mut remote: HashMap<(&str, CommandType), &Command> = remote
+        .iter()
+        .map(|command| ((command.name.as_str(), command.kind), command))
+        .collect();
+
+    let mut changes = Vec::new();
+
+    for command in local {
+        match remote.remove(&(command.name.as_str(), command.kind)) {
+            None => changes.push(CommandChange::Create(command.clone())),
+            Some(existing) => {
+                let fields = changed_fields(command, existing);
+                if fields.is_empty() {
+                    continue;
+                }
+
+                if let Some(id) = existing.id {
+                    changes.push(CommandChange::Update {
+                        id,
+                        command: command.clone(),
+                        fields,
+                    });
+                }
+            }
+        }
+    }
+
+    let mut removed: Vec<_> = remote.into_values().collect();
+    removed.sort_by(|a, b| a.name.cmp(&b.name));
+    changes.extend(removed.into_iter().filter_map(|command| {
+        Some(CommandChange::Delete {
+            id: command.id?,
+            name: command.name.clone(),
+            dev: is_dev_definition(command),
+        })
+    }));
+
+    changes
+}
+
+/// Top level fields that differ between two definitions
+fn changed_fields(local: &Command, remote: &Command) -> Vec<String> {
+    let local = normalize(local);
+    let remote = normalize(remote);
+
+    let mut fields: Vec<String> = local
+        .keys()
+        .chain(remote.keys())
+        .filter(|key| local.get(*key) != remote.get(*key))
+        .cloned()
+        .collect();
+    fields.sort();
+    fields.dedup();
+    fields
+}
+
+/// A definition as JSON, without the fields Discord sets and without default values
+fn normalize(command: &Command) -> Map<String, Value> {
+    let Ok(Value::Object(mut map)) = serde_json::to_value(command) else {
+        return Map::new();
+    };
+
+    for key in [
+        "id",
+        "application_id",
+        "guild_id",
+        "version",
+        "dm_permission",
+    ] {
+        map.remove(key);
+    }
+    // Discord reports every context and guild installs when a command doesn't say, in any order
+    for (key, default) in [
+        ("contexts", &[0_u64, 1, 2][..]),
+        ("integration_types", &[0][..]),
+    ] {
+        let is_default = match map.get_mut(key) {
+            Some(Value::Array(values)) => {
+                values.sort_by_key(Value::as_u64);
+                values
+                    .iter()
+                    .map(Value::as_u64)
+                    .eq(default.iter().copied().map(Some))
+            }
+            _ => false,
+        };
+        if is_default {
+            map.remove(key);
+        }
+    }
+
+    for value in map.values_mut() {
+        strip_defaults(value);
+    }
+    map.retain(|_, value| !is_default(value));
+    map
+}
+
+fn strip_defaults(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for value in map.values_mut() {
+                strip_defaults(value);
+            }
+            map.retain(|_, value| !is_default(value));
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_defaults),
+        _ => {}
+    }
+}
+
+fn is_default(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Bool(false) => true,
+        Value::Array(items) => items.is_empty(),
+        Value::Object(map) => map.is_empty(),
+        _ => false,
+    }
+}
+
+/// The commands Discord currently has for a target, with their localizations
+pub async fn fetch(
+    context: &Context,
+    target: DeployTarget,
+) -> Result<Vec<Command>, Box<dyn std::error::Error>> {
+    let interaction = context.client.interaction(context.application_id);
+
+    let commands = match target {
+        DeployTarget::Global => {
+            interaction
+                .global_commands()
+                .with_localizations(true)
+                .await?
+                .models()
+                .await?
+        }
+        DeployTarget::Guild(guild_id) => {
+            interaction
+                .guild_commands(guild_id)
+                .with_localizations(true)
+                .await?
+                .models()
+                .await?
+        }
+    };
+
+    Ok(commands)
+}
+
+/// Log the changes for a target and apply them unless this is a dry run
+pub async fn apply(
+    context: &Context,
+    target: DeployTarget,
+    changes: &[CommandChange],
+    options: &DeployOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if changes.is_empty() {
+        info!("Commands for {} are up to date", target);
+        return Ok(());
+    }
+
+    let prefix = if options.dry_run { "[dry run] " } else { "" };
+    info!("{}Command changes for {}:", prefix, target);
+    for change in changes {
+        info!("{}  {}", prefix, change);
+    }
+
+    if options.dry_run {
+        return Ok(());
+    }
+
+    let application_id = context.application_id.get();
+    let interaction = context.client.interaction(context.application_id);
+
+    for change in changes {
+        match change {
+            CommandChange::Create(command) => {
+                let route = match target {
+                    DeployTarget::Global => Route::CreateGlobalCommand { application_id },
+                    DeployTarget::Guild(guild_id) => Route::CreateGuildCommand {
+                        application_id,
+                        guild_id: guild_id.get(),
+                    },
+                };
+                send(context, &route, command).await?;
+            }
+            CommandChange::Update { id, command, .. } => {
+                let route = match target {
+                    DeployTarget::Global => Route::UpdateGlobalCommand {
+                        application_id,
+                        command_id: id.get(),
+                    },
+                    DeployTarget::Guild(guild_id) => Route::UpdateGuildCommand {
+                        application_id,
+                        command_id: id.get(),
+                        guild_id: guild_id.get(),
+                    },
+                };
+                send(context, &route, command).await?;
+            }
+            CommandChange::Delete { id, .. } => match target {
+                DeployTarget::Global => {
+                    interaction.delete_global_command(*id).await?;
+                }
+                DeployTarget::Guild(guild_id) => {
+                    interaction.delete_guild_command(guild_id, *id).await?;
+                }
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Send a whole definition, the typed builders don't cover every field
+async fn send(
+    context: &Context,
+    route: &Route<'_>,
+    command: &Command,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let request = Request::builder(route).json(command).build()?;
+    context.client.request::<Command>(request).await?;
+    Ok(())
+}
@@ -0,0 +1,176 @@
+use super::middleware::Target;
+use super::{CommandContext, CommandError, Locale, LocalizedText};
+use std::collections::hash_map::RandomState;
+use std::fmt::{self, Display};
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, error};
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, UserMarker};
+
+/// Characters of a reference id, without ones that are easily mixed up such as `0` and `O`
+const REFERENCE_ALPHABET: &[u8; 32] = b"23456789ABCDEFGHJKLMNPQRSTUVWXYZ";
+const REFERENCE_LENGTH: usize = 6;
+/// Longest detail put in a report, Discord messages are limited to 2000 characters
+const MAX_REPORT_DETAIL_LENGTH: usize = 1500;
+
+/// Short id of a failure, shown to the user and put in the logs so the two can be matched up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ErrorReference([u8; REFERENCE_LENGTH]);
+
+impl ErrorReference {
+    pub fn new() -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        hasher.write_u128(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_nanos())
+                .unwrap_or(0),
+        );
+        let mut bits = hasher.finish();
+
+        let mut reference = [0; REFERENCE_LENGTH];
+        for character in &mut reference {
+            *character = REFERENCE_ALPHABET[(bits % 32) as usize];
+            bits /= 32;
+        }
+
+        ErrorReference(reference)
+    }
+}
+
+impl Default for ErrorReference {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for ErrorReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Only ever built from the ASCII alphabet
+        f.write_str(std::str::from_utf8(&self.0).unwrap_or_default())
+    }
+}
+
+/// Where failures are reported besides the logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorReportTarget {
+    /// DM the bot owner
+    Owner(Id<UserMarker>),
+    /// Post in a channel only admins can see
+    Channel(Id<ChannelMarker>),
+}
+
+/// Shown for failures that don't bring their own message
+fn generic_message() -> LocalizedText {
+    LocalizedText::new("Something went wrong on our side.")
+        .with_localization(Locale::German, "Bei uns ist etwas schiefgelaufen.")
+        .with_localization(Locale::Spanish, "Algo salió mal por nuestra parte.")
+        .with_localization(Locale::French, "Un problème est survenu de notre côté.")
+}
+
+/// Tell the user what went wrong, the same way for commands, components and modals
+///
+/// Failures are logged with a new `ErrorReference` that is also added to the reply, and sent to
+/// `report_to` with everything admins need to look into them.
+pub(super) async fn report_error(
+    command_context: &mut CommandContext,
+    target: &Target<'_>,
+    error: CommandError,
+    report_to: Option<ErrorReportTarget>,
+) {
+    let message = match &error {
+        CommandError::Expired => {
+            // Replying needs the expired token as well, the log is all that's left
+            error!(
+                "{} {} could not respond to an expired interaction",
+                target.kind(),
+                target.name()
+            );
+            return;
+        }
+        CommandError::Validation(message) => {
+            debug!("{} {} rejected: {}", target.kind(), target.name(), message);
+
+            if let Err(reply_error) = command_context.reply_ephemeral(message.as_str()).await {
+                error!(
+                    "Could not send validation error {}: {}",
+                    message, reply_error
+                );
+            }
+            return;
+        }
+        CommandError::Failed { message, .. } => message.clone(),
+        CommandError::Http(_) | CommandError::Internal(_) => generic_message(),
+    };
+
+    let reference = ErrorReference::new();
+    error!(
+        reference = %reference,
+        "{} {} failed: {}",
+        target.kind(),
+        target.name(),
+        error
+    );
+
+    let locale = command_context.get_effective_locale();
+    let reply = format!("{} (ref `{}`)", message.get(locale.as_ref()), reference);
+    if let Err(reply_error) = command_context.reply_ephemeral(reply).await {
+        error!("Could not send error {}: {}", reference, reply_error);
+    }
+
+    if let Some(report_to) = report_to {
+        send_report(command_context, target, &error, reference, report_to).await;
+    }
+}
+
+/// Send the full context of a failure to the owner or the report channel
+async fn send_report(
+    command_context: &CommandContext,
+    target: &Target<'_>,
+    error: &CommandError,
+    reference: ErrorReference,
+    report_to: ErrorReportTarget,
+) {
+    let mut detail = error.to_string();
+    if detail.chars().count() > MAX_REPORT_DETAIL_LENGTH {
+        detail = detail.chars().take(MAX_REPORT_DETAIL_LENGTH).collect();
+        detail.push('…');
+    }
+
+    let mention = |id: Option<String>| id.unwrap_or_else(|| "none".to_string());
+    let content = format!(
+        "**Error `{}`** in {} `{}`\nUser: {} Guild: {} Channel: {}\n```\n{}\n```",
+        reference,
+        target.kind(),
+        target.name(),
+        mention(command_context.get_user_id().map(|id| format!("<@{}>", id))),
+        mention(command_context.get_guild_id().map(|id| format!("`{}`", id))),
+        mention(
+            command_context
+                .get_channel_id()
+                .map(|id| format!("<#{}>", id))
+        ),
+        detail.replace("```", "'''")
+    );
+
+    let context = &command_context.context;
+    let result = match report_to {
+        ErrorReportTarget::Owner(user_id) => context.send_dm_to_user(user_id, &content).await,
+        ErrorReportTarget::Channel(channel_id) => context
+            .client
+            .create_message(channel_id)
+            .content(&content)
+            .await
+            .map(|_| ())
+            .map_err(Into::into),
+    };
+
+    if let Err(report_error) = result {
+        error!("Could not report error {}: {}", reference, report_error);
+    }
+}
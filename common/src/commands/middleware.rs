@@ -0,0 +1,283 @@
+use super::access::CommandAccess;
+use super::components::{ComponentHandler, CustomId, ModalHandler};
+use super::cooldowns::{Cooldown, CooldownLayer};
+use super::{CommandBundle, CommandContext, CommandError};
+use async_trait::async_trait;
+use futures_util::FutureExt;
+use std::any::Any;
+use std::panic::AssertUnwindSafe;
+use std::time::{Duration, Instant};
+use tracing::{Instrument, debug, info_span, warn};
+use twilight_model::application::interaction::application_command::CommandData;
+use twilight_model::application::interaction::message_component::MessageComponentInteractionData;
+use twilight_model::application::interaction::modal::ModalInteractionData;
+
+/// What an interaction runs once every layer let it through
+pub enum Target<'a> {
+    Command {
+        command: &'a dyn CommandBundle,
+        data: &'a CommandData,
+    },
+    Component {
+        handler: &'a dyn ComponentHandler,
+        custom_id: &'a CustomId,
+        data: &'a MessageComponentInteractionData,
+    },
+    Modal {
+        handler: &'a dyn ModalHandler,
+        custom_id: &'a CustomId,
+        data: &'a ModalInteractionData,
+    },
+}
+
+impl Target<'_> {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Target::Command { .. } => "command",
+            Target::Component { .. } => "component",
+            Target::Modal { .. } => "modal",
+        }
+    }
+
+    /// The command name or the custom id prefix
+    pub fn name(&self) -> &str {
+        match self {
+            Target::Command { data, .. } => &data.name,
+            Target::Component { custom_id, .. } | Target::Modal { custom_id, .. } => {
+                custom_id.prefix()
+            }
+        }
+    }
+
+    /// Who may use the target and where
+    pub fn access(&self) -> CommandAccess {
+        match self {
+            Target::Command { command, .. } => command.access(),
+            Target::Component { handler, .. } => handler.access(),
+            Target::Modal { handler, .. } => handler.access(),
+        }
+    }
+
+    /// How often the target can be used, enforced by `CooldownLayer`
+    pub fn cooldown(&self) -> Option<Cooldown> {
+        match self {
+            Target::Command { command, .. } => command.cooldown(),
+            Target::Component { handler, .. } => handler.cooldown(),
+            Target::Modal { handler, .. } => handler.cooldown(),
+        }
+    }
+
+    async fn run(&self, context: &mut CommandContext) -> Result<(), CommandError> {
+        match self {
+            Target::Command { command, data } => command.execute(context, data).await,
+            Target::Component {
+                handler,
+                custom_id,
+                data,
+            } => handler.handle(context, custom_id, data).await,
+            Target::Modal {
+                handler,
+                custom_id,
+                data,
+            } => handler.submit(context, custom_id, data).await,
+        }
+    }
+}
+
+/// A layer around every command, component and modal
+///
+/// A layer does its work before and after calling `next.run`. Returning without calling it
+/// stops the interaction there, after replying with `context` or by returning an error.
+///
+/// ```ignore
+/// struct Maintenance;
+///
+/// #[async_trait]
+/// impl Middleware for Maintenance {
+///     async fn handle(
+///         &self,
+///         context: &mut CommandContext,
+///         _target: &Target<'_>,
+///         _next: Next<'_>,
+///     ) -> Result<(), CommandError> {
+///         context.reply_ephemeral("Back in a few minutes").await
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    async fn handle(
+        &self,
+        context: &mut CommandContext,
+        target: &Target<'_>,
+        next: Next<'_>,
+    ) -> Result<(), CommandError>;
+}
+
+/// The layers after the current one, followed by the target
+pub struct Next<'a> {
+    layers: &'a [Box<dyn Middleware>],
+    target: &'a Target<'a>,
+}
+
+impl<'a> Next<'a> {
+    pub(super) fn new(layers: &'a [Box<dyn Middleware>], target: &'a Target<'a>) -> Self {
+        Self { layers, target }
+    }
+
+    pub async fn run(self, context: &mut CommandContext) -> Result<(), CommandError> {
+        match self.layers.split_first() {
+            Some((layer, rest)) => {
+                let next = Next::new(rest, self.target);
+                layer.handle(context, self.target, next).await
+            }
+            None => self.target.run(context).await,
+        }
+    }
+}
+
+/// The layers every registry starts with, outermost first
+pub fn default_layers() -> Vec<Box<dyn Middleware>> {
+    vec![
+        Box::new(TracingLayer),
+        Box::new(PanicLayer),
+        Box::new(TimingLayer::new(Duration::from_secs(2))),
+        Box::new(GuildOnlyLayer),
+        Box::new(PermissionLayer),
+        Box::new(CooldownLayer::new()),
+    ]
+}
+
+/// Runs the rest of the chain in a span with the interaction's details
+pub struct TracingLayer;
+
+#[async_trait]
+impl Middleware for TracingLayer {
+    async fn handle(
+        &self,
+        context: &mut CommandContext,
+        target: &Target<'_>,
+        next: Next<'_>,
+    ) -> Result<(), CommandError> {
+        let span = info_span!(
+            "interaction",
+            id = %context.interaction.id,
+            kind = target.kind(),
+            name = target.name(),
+            user = ?context.get_user_id(),
+            guild = ?context.get_guild_id(),
+        );
+
+        next.run(context).instrument(span).await
+    }
+}
+
+/// Turns a panic into an error reply instead of leaving the interaction unanswered
+pub struct PanicLayer;
+
+#[async_trait]
+impl Middleware for PanicLayer {
+    async fn handle(
+        &self,
+        context: &mut CommandContext,
+        target: &Target<'_>,
+        next: Next<'_>,
+    ) -> Result<(), CommandError> {
+        match AssertUnwindSafe(next.run(context)).catch_unwind().await {
+            Ok(result) => result,
+            // Logged with the failure's reference id like any other internal error
+            Err(panic) => Err(CommandError::Internal(format!(
+                "panicked: {}",
+                panic_message(&*panic)
+            ))),
+        }
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// Logs how long interactions take to be answered
+///
+/// Commands that wait for components keep running long after they responded, so only the time
+/// to the first response is held against `slow`.
+pub struct TimingLayer {
+    slow: Duration,
+}
+
+impl TimingLayer {
+    pub fn new(slow: Duration) -> Self {
+        Self { slow }
+    }
+}
+
+#[async_trait]
+impl Middleware for TimingLayer {
+    async fn handle(
+        &self,
+        context: &mut CommandContext,
+        target: &Target<'_>,
+        next: Next<'_>,
+    ) -> Result<(), CommandError> {
+        let start = Instant::now();
+        let result = next.run(context).await;
+
+        let responded = context
+            .responded
+            .map(|responded| responded.saturating_duration_since(start));
+        debug!(
+            "{} {} responded after {:?}, finished after {:?}",
+            target.kind(),
+            target.name(),
+            responded,
+            start.elapsed()
+        );
+        if let Some(responded) = responded.filter(|responded| *responded > self.slow) {
+            warn!(
+                "{} {} took {:?} to respond, consider deferring",
+                target.kind(),
+                target.name(),
+                responded
+            );
+        }
+
+        result
+    }
+}
+
+/// Stops targets from running where their access doesn't allow, e.g. guild commands in DMs
+pub struct GuildOnlyLayer;
+
+#[async_trait]
+impl Middleware for GuildOnlyLayer {
+    async fn handle(
+        &self,
+        context: &mut CommandContext,
+        target: &Target<'_>,
+        next: Next<'_>,
+    ) -> Result<(), CommandError> {
+        target.access().check_context(context)?;
+        next.run(context).await
+    }
+}
+
+/// Checks the member permissions of a target again, guild admins can override Discord's checks
+pub struct PermissionLayer;
+
+#[async_trait]
+impl Middleware for PermissionLayer {
+    async fn handle(
+        &self,
+        context: &mut CommandContext,
+        target: &Target<'_>,
+        next: Next<'_>,
+    ) -> Result<(), CommandError> {
+        target.access().check_permissions(context)?;
+        next.run(context).await
+    }
+}
@@ -1,49 +1,179 @@
 use crate::context::Context;
+use access::CommandAccess;
 use async_trait::async_trait;
+use autocomplete::FocusedOption;
+use cooldowns::Cooldown;
+use modals::{Modal, ModalForm};
+use options::ChoiceValue;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::Arc;
-use twilight_model::application::command::Command as ApplicationCommand;
-use twilight_model::application::interaction::Interaction;
+use std::time::{Duration, Instant};
+use twilight_model::application::command::{
+    Command as ApplicationCommand, CommandOptionChoiceValue,
+};
 use twilight_model::application::interaction::application_command::{
     CommandData, CommandOptionValue,
 };
-use twilight_model::channel::message::{AllowedMentions, Component, Embed, MessageFlags};
+use twilight_model::application::interaction::{Interaction, InteractionContextType};
+use twilight_model::channel::message::{AllowedMentions, Component, Embed, Message, MessageFlags};
+use twilight_model::guild::Permissions;
 use twilight_model::http::attachment::Attachment;
 use twilight_model::http::interaction::{
     InteractionResponse, InteractionResponseData, InteractionResponseType,
 };
-use twilight_model::id::marker::AttachmentMarker;
+use twilight_model::id::marker::{
+    AttachmentMarker, ChannelMarker, MessageMarker, RoleMarker, UserMarker,
+};
 use twilight_model::id::{Id, marker::GuildMarker};
 
+pub mod access;
+pub mod autocomplete;
+pub mod collectors;
+pub mod components;
+pub mod cooldowns;
+pub mod definition;
+pub mod deploy;
+pub mod errors;
+pub mod middleware;
+pub mod modals;
+pub mod options;
+pub mod pagination;
 pub mod registry;
+pub mod subcommands;
+pub mod translations;
 
-/// Supported locales for Discord commands
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Every locale Discord supports, see <https://discord.com/developers/docs/reference#locales>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Locale {
-    EnglishUS,
+    Indonesian,
+    Danish,
+    German,
     EnglishGB,
+    EnglishUS,
     Spanish,
+    SpanishLatinAmerica,
     French,
-    German,
+    Croatian,
+    Italian,
+    Lithuanian,
+    Hungarian,
+    Dutch,
+    Norwegian,
+    Polish,
+    PortugueseBrazil,
+    Romanian,
+    Finnish,
+    Swedish,
+    Vietnamese,
+    Turkish,
+    Czech,
+    Greek,
+    Bulgarian,
+    Russian,
+    Ukrainian,
+    Hindi,
+    Thai,
+    Chinese,
     Japanese,
+    ChineseTaiwan,
     Korean,
-    Chinese,
 }
 
 impl Locale {
+    pub const ALL: [Locale; 32] = [
+        Locale::Indonesian,
+        Locale::Danish,
+        Locale::German,
+        Locale::EnglishGB,
+        Locale::EnglishUS,
+        Locale::Spanish,
+        Locale::SpanishLatinAmerica,
+        Locale::French,
+        Locale::Croatian,
+        Locale::Italian,
+        Locale::Lithuanian,
+        Locale::Hungarian,
+        Locale::Dutch,
+        Locale::Norwegian,
+        Locale::Polish,
+        Locale::PortugueseBrazil,
+        Locale::Romanian,
+        Locale::Finnish,
+        Locale::Swedish,
+        Locale::Vietnamese,
+        Locale::Turkish,
+        Locale::Czech,
+        Locale::Greek,
+        Locale::Bulgarian,
+        Locale::Russian,
+        Locale::Ukrainian,
+        Locale::Hindi,
+        Locale::Thai,
+        Locale::Chinese,
+        Locale::Japanese,
+        Locale::ChineseTaiwan,
+        Locale::Korean,
+    ];
+
     pub fn as_str(&self) -> &'static str {
         match self {
-            Locale::EnglishUS => "en-US",
+            Locale::Indonesian => "id",
+            Locale::Danish => "da",
+            Locale::German => "de",
             Locale::EnglishGB => "en-GB",
+            Locale::EnglishUS => "en-US",
             Locale::Spanish => "es-ES",
+            Locale::SpanishLatinAmerica => "es-419",
             Locale::French => "fr",
-            Locale::German => "de",
+            Locale::Croatian => "hr",
+            Locale::Italian => "it",
+            Locale::Lithuanian => "lt",
+            Locale::Hungarian => "hu",
+            Locale::Dutch => "nl",
+            Locale::Norwegian => "no",
+            Locale::Polish => "pl",
+            Locale::PortugueseBrazil => "pt-BR",
+            Locale::Romanian => "ro",
+            Locale::Finnish => "fi",
+            Locale::Swedish => "sv-SE",
+            Locale::Vietnamese => "vi",
+            Locale::Turkish => "tr",
+            Locale::Czech => "cs",
+            Locale::Greek => "el",
+            Locale::Bulgarian => "bg",
+            Locale::Russian => "ru",
+            Locale::Ukrainian => "uk",
+            Locale::Hindi => "hi",
+            Locale::Thai => "th",
+            Locale::Chinese => "zh-CN",
             Locale::Japanese => "ja",
+            Locale::ChineseTaiwan => "zh-TW",
             Locale::Korean => "ko",
-            Locale::Chinese => "zh-CN",
         }
     }
+
+    /// Parse a Discord locale code such as `en-US`
+    pub fn from_discord(locale: &str) -> Option<Locale> {
+        Locale::ALL
+            .into_iter()
+            .find(|candidate| candidate.as_str() == locale)
+    }
+
+    /// The closest locale to use when there is no text for this one
+    pub fn fallback(&self) -> Option<Locale> {
+        match self {
+            Locale::SpanishLatinAmerica => Some(Locale::Spanish),
+            Locale::EnglishGB => Some(Locale::EnglishUS),
+            Locale::ChineseTaiwan => Some(Locale::Chinese),
+            _ => None,
+        }
+    }
+
+    /// This locale followed by its fallbacks, e.g. `es-419`, `es-ES`
+    pub fn fallback_chain(&self) -> impl Iterator<Item = Locale> {
+        std::iter::successors(Some(*self), Locale::fallback)
+    }
 }
 
 impl From<Locale> for String {
@@ -71,6 +201,17 @@ impl LocalizedText {
         self
     }
 
+    /// The text for a locale, following its fallback chain and then the default text
+    pub fn get(&self, locale: Option<&Locale>) -> &str {
+        locale
+            .and_then(|locale| {
+                locale
+                    .fallback_chain()
+                    .find_map(|locale| self.localizations.get(&locale))
+            })
+            .unwrap_or(&self.default)
+    }
+
     pub fn to_discord_localizations(&self) -> Option<HashMap<String, String>> {
         if self.localizations.is_empty() {
             None
@@ -85,6 +226,18 @@ impl LocalizedText {
     }
 }
 
+impl From<&str> for LocalizedText {
+    fn from(text: &str) -> Self {
+        LocalizedText::new(text)
+    }
+}
+
+impl From<String> for LocalizedText {
+    fn from(text: String) -> Self {
+        LocalizedText::new(text)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum CommandScope {
     Global,
@@ -102,14 +255,32 @@ pub struct CommandContext {
     pub context: Arc<Context>,
     interaction: Interaction,
     has_replied: bool,
+    /// When the interaction arrived, its token stops working `TOKEN_LIFETIME` later
+    received: Instant,
 }
 
+/// How long Discord accepts responses and follow-ups for an interaction
+pub const TOKEN_LIFETIME: Duration = Duration::from_secs(15 * 60);
+
 impl CommandContext {
     pub fn new(context: Arc<Context>, interaction: Interaction) -> Self {
         Self {
             context,
             interaction,
             has_replied: false,
+            received: Instant::now(),
+        }
+    }
+
+    /// Whether the interaction token can still be used
+    pub fn is_expired(&self) -> bool {
+        self.received.elapsed() >= TOKEN_LIFETIME
+    }
+
+    fn check_token(&self) -> Result<(), CommandError> {
+        match self.is_expired() {
+            true => Err(CommandError::Expired),
+            false => Ok(()),
         }
     }
 
@@ -117,27 +288,54 @@ impl CommandContext {
         self.interaction.guild_id
     }
 
+    pub fn get_channel_id(&self) -> Option<Id<ChannelMarker>> {
+        self.interaction.channel.as_ref().map(|channel| channel.id)
+    }
+
+    /// Get the id of the user that triggered the interaction
+    pub fn get_user_id(&self) -> Option<Id<UserMarker>> {
+        self.interaction.author_id()
+    }
+
+    /// Get the invoking member's permissions in the channel, `None` outside of guilds
+    pub fn get_member_permissions(&self) -> Option<Permissions> {
+        self.interaction
+            .member
+            .as_ref()
+            .and_then(|member| member.permissions)
+    }
+
     /// Get the user's preferred locale
     pub fn get_locale(&self) -> Option<Locale> {
         self.interaction
             .locale
-            .as_ref()
-            .and_then(|l| match l.as_str() {
-                "en-US" => Some(Locale::EnglishUS),
-                "en-GB" => Some(Locale::EnglishGB),
-                "es-ES" => Some(Locale::Spanish),
-                "fr" => Some(Locale::French),
-                "de" => Some(Locale::German),
-                "ja" => Some(Locale::Japanese),
-                "ko" => Some(Locale::Korean),
-                "zh-CN" => Some(Locale::Chinese),
-                _ => None,
-            })
+            .as_deref()
+            .and_then(Locale::from_discord)
+    }
+
+    /// Get the user's locale, falling back to the guild's preferred locale
+    pub fn get_effective_locale(&self) -> Option<Locale> {
+        self.get_locale().or_else(|| self.get_guild_locale())
+    }
+
+    /// Get where the interaction happened, e.g. a guild or a DM with the bot
+    pub fn get_interaction_context(&self) -> Option<InteractionContextType> {
+        self.interaction.context
+    }
+
+    /// Get the guild's preferred locale
+    pub fn get_guild_locale(&self) -> Option<Locale> {
+        self.interaction
+            .guild_locale
+            .as_deref()
+            .and_then(Locale::from_discord)
     }
 
     /// Send a response to the interaction
     /// Will update the response if a response has already been given (by this function)
     pub async fn respond(&mut self, response: InteractionResponse) -> Result<(), CommandError> {
+        self.check_token()?;
+
         match self.has_replied {
             true => {
                 if let Some(data) = response.data {
@@ -172,17 +370,30 @@ impl CommandContext {
         }
     }
 
-    /// Gives that says the bot is thinking
+    /// Acknowledge the interaction and show that the bot is thinking
+    ///
+    /// Use this when the answer can take longer than Discord's 3 second limit, later replies
+    /// edit the deferred response. `ephemeral` can't be changed afterwards.
+    pub async fn defer(&mut self, ephemeral: bool) -> Result<(), CommandError> {
+        self.respond(InteractionResponse {
+            kind: InteractionResponseType::DeferredChannelMessageWithSource,
+            data: Some(InteractionResponseData {
+                flags: ephemeral.then_some(MessageFlags::EPHEMERAL),
+                ..Default::default()
+            }),
+        })
+        .await
+    }
+
+    /// Acknowledge a component interaction without changing its message yet
+    ///
+    /// Only valid for components, commands should use `defer`.
     pub async fn deferred_update_message(&mut self) -> Result<(), CommandError> {
         self.respond(InteractionResponse {
             kind: InteractionResponseType::DeferredUpdateMessage,
             data: None,
         })
-        .await?;
-
-        self.has_replied = true;
-
-        Ok(())
+        .await
     }
 
     /// Updates a response to the interaction
@@ -195,6 +406,8 @@ impl CommandContext {
         attachment_ids_to_keep: &[Id<AttachmentMarker>],
         attachments: &[Attachment],
     ) -> Result<(), CommandError> {
+        self.check_token()?;
+
         self.context
             .client
             .interaction(self.interaction.application_id)
@@ -239,6 +452,180 @@ impl CommandContext {
         self.respond(response).await
     }
 
+    /// Reply with an embed
+    pub async fn reply_embed(&mut self, embed: Embed, ephemeral: bool) -> Result<(), CommandError> {
+        let response = InteractionResponse {
+            kind: InteractionResponseType::ChannelMessageWithSource,
+            data: Some(InteractionResponseData {
+                embeds: Some(vec![embed]),
+                flags: ephemeral.then_some(MessageFlags::EPHEMERAL),
+                ..Default::default()
+            }),
+        };
+        self.respond(response).await
+    }
+
+    /// Reply with a message that has buttons or select menus
+    pub async fn reply_components<S: Into<String>>(
+        &mut self,
+        content: S,
+        components: Vec<Component>,
+        ephemeral: bool,
+    ) -> Result<(), CommandError> {
+        let response = InteractionResponse {
+            kind: InteractionResponseType::ChannelMessageWithSource,
+            data: Some(InteractionResponseData {
+                content: Some(content.into()),
+                components: Some(components),
+                flags: ephemeral.then_some(MessageFlags::EPHEMERAL),
+                ..Default::default()
+            }),
+        };
+        self.respond(response).await
+    }
+
+    /// Get the first response to the interaction
+    pub async fn get_response(&self) -> Result<Message, CommandError> {
+        self.check_token()?;
+
+        self.context
+            .client
+            .interaction(self.interaction.application_id)
+            .response(&self.interaction.token)
+            .await
+            .map_err(CommandError::Http)?
+            .model()
+            .await
+            .map_err(|error| CommandError::Internal(format!("Could not read response: {}", error)))
+    }
+
+    /// Delete the first response to the interaction
+    pub async fn delete_response(&self) -> Result<(), CommandError> {
+        self.check_token()?;
+
+        self.context
+            .client
+            .interaction(self.interaction.application_id)
+            .delete_response(&self.interaction.token)
+            .await
+            .map_err(CommandError::Http)?;
+        Ok(())
+    }
+
+    /// Send another message after the first response, returns it so it can be edited later
+    pub async fn followup(
+        &self,
+        content: &str,
+        embeds: &[Embed],
+        components: &[Component],
+        ephemeral: bool,
+    ) -> Result<Message, CommandError> {
+        self.check_token()?;
+
+        // Discord only answers follow-ups without a response with a 404
+        if !self.has_replied {
+            return Err(CommandError::Internal(
+                "Follow-up sent before responding to the interaction".into(),
+            ));
+        }
+
+        let interaction = self
+            .context
+            .client
+            .interaction(self.interaction.application_id);
+        let mut followup = interaction
+            .create_followup(&self.interaction.token)
+            .content(content)
+            .embeds(embeds)
+            .components(components);
+        if ephemeral {
+            followup = followup.flags(MessageFlags::EPHEMERAL);
+        }
+
+        followup
+            .await
+            .map_err(CommandError::Http)?
+            .model()
+            .await
+            .map_err(|error| CommandError::Internal(format!("Could not read follow-up: {}", error)))
+    }
+
+    /// Edit a follow-up message, `None` leaves that part unchanged
+    pub async fn update_followup(
+        &self,
+        message_id: Id<MessageMarker>,
+        content: Option<&str>,
+        embeds: Option<&[Embed]>,
+        components: Option<&[Component]>,
+    ) -> Result<(), CommandError> {
+        self.check_token()?;
+
+        self.context
+            .client
+            .interaction(self.interaction.application_id)
+            .update_followup(&self.interaction.token, message_id)
+            .content(content)
+            .embeds(embeds)
+            .components(components)
+            .await
+            .map_err(CommandError::Http)?;
+        Ok(())
+    }
+
+    pub async fn delete_followup(&self, message_id: Id<MessageMarker>) -> Result<(), CommandError> {
+        self.check_token()?;
+
+        self.context
+            .client
+            .interaction(self.interaction.application_id)
+            .delete_followup(&self.interaction.token, message_id)
+            .await
+            .map_err(CommandError::Http)?;
+        Ok(())
+    }
+
+    /// Edit the message the component is attached to
+    pub async fn update_message<S: Into<String>>(
+        &mut self,
+        content: S,
+        components: Vec<Component>,
+    ) -> Result<(), CommandError> {
+        let response = InteractionResponse {
+            kind: InteractionResponseType::UpdateMessage,
+            data: Some(InteractionResponseData {
+                content: Some(content.into()),
+                components: Some(components),
+                ..Default::default()
+            }),
+        };
+        self.respond(response).await
+    }
+
+    /// Open a modal, its custom id picks the `ModalHandler` that gets the submission
+    pub async fn show_modal<S: Into<String>>(
+        &mut self,
+        custom_id: String,
+        title: S,
+        components: Vec<Component>,
+    ) -> Result<(), CommandError> {
+        let response = InteractionResponse {
+            kind: InteractionResponseType::Modal,
+            data: Some(InteractionResponseData {
+                custom_id: Some(custom_id),
+                title: Some(title.into()),
+                components: Some(components),
+                ..Default::default()
+            }),
+        };
+        self.respond(response).await
+    }
+
+    /// Open a modal for a `ModalForm`, its `FormHandler` gets the parsed submission
+    pub async fn open_modal<F: ModalForm>(&mut self, modal: Modal<F>) -> Result<(), CommandError> {
+        let (custom_id, title, components) = modal.build()?;
+        self.show_modal(custom_id, title, components).await
+    }
+
     /// Respond with autocomplete choices
     pub async fn autocomplete(
         &mut self,
@@ -275,13 +662,71 @@ impl CommandContext {
                 _ => None,
             })
     }
+
+    /// Get a boolean option value
+    pub fn get_boolean_option(&self, name: &str, data: &CommandData) -> Option<bool> {
+        data.options
+            .iter()
+            .find(|opt| opt.name == name)
+            .and_then(|opt| match &opt.value {
+                CommandOptionValue::Boolean(b) => Some(*b),
+                _ => None,
+            })
+    }
+
+    /// Get a role option value
+    pub fn get_role_option(&self, name: &str, data: &CommandData) -> Option<Id<RoleMarker>> {
+        data.options
+            .iter()
+            .find(|opt| opt.name == name)
+            .and_then(|opt| match &opt.value {
+                CommandOptionValue::Role(role_id) => Some(*role_id),
+                _ => None,
+            })
+    }
+
+    /// Get a channel option value
+    pub fn get_channel_option(&self, name: &str, data: &CommandData) -> Option<Id<ChannelMarker>> {
+        data.options
+            .iter()
+            .find(|opt| opt.name == name)
+            .and_then(|opt| match &opt.value {
+                CommandOptionValue::Channel(channel_id) => Some(*channel_id),
+                _ => None,
+            })
+    }
+
+    /// Get a user option value
+    pub fn get_user_option(&self, name: &str, data: &CommandData) -> Option<Id<UserMarker>> {
+        data.options
+            .iter()
+            .find(|opt| opt.name == name)
+            .and_then(|opt| match &opt.value {
+                CommandOptionValue::User(user_id) => Some(*user_id),
+                _ => None,
+            })
+    }
 }
 
 /// Autocomplete choice for command options
 #[derive(Debug, Clone)]
 pub struct AutocompleteChoice {
     pub name: LocalizedText,
-    pub value: String,
+    /// Must match the type of the focused option
+    pub value: CommandOptionChoiceValue,
+}
+
+impl AutocompleteChoice {
+    pub fn new(name: impl Into<String>, value: impl ChoiceValue) -> Self {
+        Self::localized(LocalizedText::new(name), value)
+    }
+
+    pub fn localized(name: LocalizedText, value: impl ChoiceValue) -> Self {
+        Self {
+            name,
+            value: value.into_choice(),
+        }
+    }
 }
 
 impl From<AutocompleteChoice> for twilight_model::application::command::CommandOptionChoice {
@@ -291,19 +736,39 @@ impl From<AutocompleteChoice> for twilight_model::application::command::CommandO
         Self {
             name: localized_text.default.clone(),
             name_localizations: localized_text.to_discord_localizations(),
-            value: twilight_model::application::command::CommandOptionChoiceValue::String(
-                choice.value,
-            ),
+            value: choice.value,
         }
     }
 }
 
 /// Error type for command operations
+///
+/// Only `Validation` and the message of `Failed` are shown to users. Everything else is logged
+/// under a reference id, and users get a generic message with that id to pass on to admins.
 #[derive(Debug)]
 pub enum CommandError {
     Http(twilight_http::Error),
+    /// The input can't be used, the message is shown to the user as is
     Validation(String),
+    /// Something broke on our side, the message is only logged
     Internal(String),
+    /// Something broke, with a message for the user and details for the logs
+    Failed {
+        message: LocalizedText,
+        detail: String,
+    },
+    /// The interaction token is older than `TOKEN_LIFETIME`, nothing can be sent anymore
+    Expired,
+}
+
+impl CommandError {
+    /// A failure users should hear about, e.g. `failed("Could not save settings!", error)`
+    pub fn failed(message: impl Into<LocalizedText>, detail: impl std::fmt::Display) -> Self {
+        CommandError::Failed {
+            message: message.into(),
+            detail: detail.to_string(),
+        }
+    }
 }
 
 impl std::fmt::Display for CommandError {
@@ -312,6 +777,14 @@ impl std::fmt::Display for CommandError {
             CommandError::Http(e) => write!(f, "HTTP error: {}", e),
             CommandError::Validation(msg) => write!(f, "Validation error: {}", msg),
             CommandError::Internal(msg) => write!(f, "Internal error: {}", msg),
+            CommandError::Failed { message, detail } => {
+                write!(f, "{} {}", message.default, detail)
+            }
+            CommandError::Expired => write!(
+                f,
+                "The interaction is older than {} minutes and can't be responded to",
+                TOKEN_LIFETIME.as_secs() / 60
+            ),
         }
     }
 }
@@ -331,15 +804,28 @@ pub trait CommandBundle: Send + Sync {
         data: &CommandData,
     ) -> Result<(), CommandError>;
 
-    /// Handle autocomplete for this command (optional)
+    /// Suggest values for the focused option (optional)
+    ///
+    /// The registry sends the returned choices, so this shouldn't respond itself.
     async fn autocomplete(
         &self,
-        _context: CommandContext,
+        _context: &CommandContext,
         _data: &CommandData,
+        _focused: &FocusedOption,
     ) -> Result<Vec<AutocompleteChoice>, CommandError> {
         Ok(Vec::new())
     }
 
+    /// Who may use the command and where, applied to the definition on deploy
+    fn access(&self) -> CommandAccess {
+        CommandAccess::default()
+    }
+
+    /// How often the command can be used, `None` for no limit
+    fn cooldown(&self) -> Option<Cooldown> {
+        None
+    }
+
     /// Get the command name
     fn name(&self) -> String {
         self.definition().name.clone()
@@ -0,0 +1,274 @@
+use super::access::CommandAccess;
+use super::components::{CustomId, ModalHandler, modal_value};
+use super::cooldowns::Cooldown;
+use super::{CommandContext, CommandError};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use twilight_model::application::interaction::modal::ModalInteractionData;
+use twilight_model::channel::message::Component;
+use twilight_model::channel::message::component::{ActionRow, TextInput, TextInputStyle};
+
+/// Most text inputs a modal can have
+const MAX_FIELDS: usize = 5;
+/// Longest text Discord allows in a text input
+const MAX_FIELD_LENGTH: u16 = 4000;
+
+/// A text input of a modal and the constraints its value has to meet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModalField {
+    pub custom_id: &'static str,
+    pub style: TextInputStyle,
+    pub min_length: Option<u16>,
+    pub max_length: Option<u16>,
+    pub required: bool,
+}
+
+impl ModalField {
+    /// A required single line input
+    pub const fn short(custom_id: &'static str) -> Self {
+        Self {
+            custom_id,
+            style: TextInputStyle::Short,
+            min_length: None,
+            max_length: None,
+            required: true,
+        }
+    }
+
+    /// A required multi line input
+    pub const fn paragraph(custom_id: &'static str) -> Self {
+        Self {
+            style: TextInputStyle::Paragraph,
+            ..Self::short(custom_id)
+        }
+    }
+
+    pub const fn length(mut self, min_length: u16, max_length: u16) -> Self {
+        self.min_length = Some(min_length);
+        self.max_length = Some(max_length);
+        self
+    }
+
+    pub const fn optional(mut self) -> Self {
+        self.required = false;
+        self
+    }
+
+    /// Check a submitted value, Discord enforces this in the client but submissions can be forged
+    fn check(&self, value: &str) -> Result<(), CommandError> {
+        let length = value.chars().count();
+
+        if length == 0 {
+            return match self.required {
+                true => Err(CommandError::Validation(format!(
+                    "`{}` is required",
+                    self.custom_id
+                ))),
+                false => Ok(()),
+            };
+        }
+
+        let min_length = self.min_length.unwrap_or(0) as usize;
+        let max_length = self.max_length.unwrap_or(MAX_FIELD_LENGTH) as usize;
+        if length < min_length || length > max_length {
+            return Err(CommandError::Validation(format!(
+                "`{}` must be {} to {} characters",
+                self.custom_id, min_length, max_length
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// The checked values of a submitted modal, by field custom id
+pub struct ModalValues<'a> {
+    values: HashMap<&'static str, &'a str>,
+}
+
+impl ModalValues<'_> {
+    /// The value of a field, `None` when an optional field was left empty
+    pub fn get(&self, custom_id: &str) -> Option<&str> {
+        self.values
+            .get(custom_id)
+            .copied()
+            .filter(|value| !value.is_empty())
+    }
+
+    pub fn required(&self, custom_id: &str) -> Result<&str, CommandError> {
+        self.get(custom_id)
+            .ok_or_else(|| CommandError::Validation(format!("`{}` is required", custom_id)))
+    }
+}
+
+/// The typed contents of a modal
+///
+/// ```ignore
+/// struct ReportForm {
+///     reason: String,
+/// }
+///
+/// impl ModalForm for ReportForm {
+///     const FIELDS: &'static [ModalField] = &[ModalField::paragraph("reason").length(10, 1000)];
+///
+///     fn from_values(values: &ModalValues) -> Result<Self, CommandError> {
+///         Ok(ReportForm { reason: values.required("reason")?.to_string() })
+///     }
+/// }
+/// ```
+pub trait ModalForm: Sized + Send {
+    /// The fields in the order they are shown, at most 5
+    const FIELDS: &'static [ModalField];
+
+    fn from_values(values: &ModalValues<'_>) -> Result<Self, CommandError>;
+
+    /// Check every field of a submission against `FIELDS` and parse it
+    fn parse(data: &ModalInteractionData) -> Result<Self, CommandError> {
+        let mut values = HashMap::new();
+
+        for field in Self::FIELDS {
+            let value = modal_value(data, field.custom_id).unwrap_or_default();
+            field.check(value)?;
+            values.insert(field.custom_id, value);
+        }
+
+        Self::from_values(&ModalValues { values })
+    }
+}
+
+/// Text shown for a field, the constraints come from `ModalField`
+#[derive(Debug, Clone, Default)]
+struct FieldText {
+    label: String,
+    placeholder: Option<String>,
+    value: Option<String>,
+}
+
+/// A modal for a `ModalForm`, shown with `CommandContext::open_modal`
+///
+/// Labels are set when the modal is shown so they can be localized.
+pub struct Modal<F: ModalForm> {
+    custom_id: CustomId,
+    title: String,
+    texts: HashMap<&'static str, FieldText>,
+    form: PhantomData<F>,
+}
+
+impl<F: ModalForm> Modal<F> {
+    pub fn new(custom_id: CustomId, title: impl Into<String>) -> Self {
+        Self {
+            custom_id,
+            title: title.into(),
+            texts: HashMap::new(),
+            form: PhantomData,
+        }
+    }
+
+    pub fn label(mut self, custom_id: &'static str, label: impl Into<String>) -> Self {
+        self.texts.entry(custom_id).or_default().label = label.into();
+        self
+    }
+
+    pub fn placeholder(mut self, custom_id: &'static str, placeholder: impl Into<String>) -> Self {
+        self.texts.entry(custom_id).or_default().placeholder = Some(placeholder.into());
+        self
+    }
+
+    /// Pre-fill a field
+    pub fn value(mut self, custom_id: &'static str, value: impl Into<String>) -> Self {
+        self.texts.entry(custom_id).or_default().value = Some(value.into());
+        self
+    }
+
+    /// The custom id, title and rows of the modal
+    pub fn build(mut self) -> Result<(String, String, Vec<Component>), CommandError> {
+        if F::FIELDS.len() > MAX_FIELDS {
+            return Err(CommandError::Internal(format!(
+                "Modals can have at most {} fields",
+                MAX_FIELDS
+            )));
+        }
+
+        let rows = F::FIELDS
+            .iter()
+            .map(|field| {
+                let text = self.texts.remove(field.custom_id).unwrap_or_default();
+                if text.label.is_empty() {
+                    return Err(CommandError::Internal(format!(
+                        "Modal field `{}` has no label",
+                        field.custom_id
+                    )));
+                }
+
+                Ok(Component::ActionRow(ActionRow {
+                    id: None,
+                    components: vec![Component::TextInput(TextInput {
+                        id: None,
+                        custom_id: field.custom_id.to_string(),
+                        label: text.label,
+                        max_length: field.max_length,
+                        min_length: field.min_length,
+                        placeholder: text.placeholder,
+                        required: Some(field.required),
+                        style: field.style,
+                        value: text.value,
+                    })],
+                }))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((self.custom_id.build()?, self.title, rows))
+    }
+}
+
+/// Handles a submitted `ModalForm`, routed by custom id prefix like any `ModalHandler`
+#[async_trait]
+pub trait FormHandler: Send + Sync {
+    type Form: ModalForm;
+
+    /// The custom id prefix this handler owns
+    fn prefix(&self) -> &str;
+
+    async fn submit(
+        &self,
+        context: &mut CommandContext,
+        custom_id: &CustomId,
+        form: Self::Form,
+    ) -> Result<(), CommandError>;
+
+    /// Who may submit the form and where
+    fn access(&self) -> CommandAccess {
+        CommandAccess::default()
+    }
+
+    /// How often the form can be submitted, `None` for no limit
+    fn cooldown(&self) -> Option<Cooldown> {
+        None
+    }
+}
+
+#[async_trait]
+impl<H: FormHandler> ModalHandler for H {
+    fn prefix(&self) -> &str {
+        FormHandler::prefix(self)
+    }
+
+    async fn submit(
+        &self,
+        context: &mut CommandContext,
+        custom_id: &CustomId,
+        data: &ModalInteractionData,
+    ) -> Result<(), CommandError> {
+        let form = H::Form::parse(data)?;
+        FormHandler::submit(self, context, custom_id, form).await
+    }
+
+    fn access(&self) -> CommandAccess {
+        FormHandler::access(self)
+    }
+
+    fn cooldown(&self) -> Option<Cooldown> {
+        FormHandler::cooldown(self)
+    }
+}
@@ -0,0 +1,306 @@
+use super::CommandError;
+use twilight_model::application::command::{
+    CommandOption, CommandOptionChoiceValue, CommandOptionType,
+};
+use twilight_model::application::interaction::application_command::{
+    CommandData, CommandDataOption, CommandOptionValue,
+};
+use twilight_model::application::interaction::{
+    InteractionChannel, InteractionDataResolved, InteractionMember,
+};
+use twilight_model::channel::Attachment;
+use twilight_model::guild::Role;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{
+    AttachmentMarker, ChannelMarker, GenericMarker, RoleMarker, UserMarker,
+};
+use twilight_model::user::User;
+
+pub use common_derive::FromCommandData;
+
+/// Command arguments parsed from the interaction, usually derived
+///
+/// The derive turns every field into an option: `Option<T>` fields are optional, the rest are
+/// required. `options` gives the matching option list for `CommandBundle::definition`, so the
+/// declared schema and the parser can't drift apart.
+///
+/// ```ignore
+/// #[derive(FromCommandData)]
+/// struct WhoisOptions {
+///     /// The member to look up
+///     user: Id<UserMarker>,
+///     #[option(rename = "private", description = "Only show the answer to you")]
+///     ephemeral: Option<bool>,
+/// }
+/// ```
+pub trait FromCommandData: Sized {
+    /// Parse the options of a command, subcommand or subcommand group
+    fn from_options(
+        options: &[CommandDataOption],
+        resolved: Option<&InteractionDataResolved>,
+    ) -> Result<Self, CommandError>;
+
+    /// The options this type parses
+    fn options() -> Vec<CommandOption>;
+
+    fn from_command_data(data: &CommandData) -> Result<Self, CommandError> {
+        Self::from_options(&data.options, data.resolved.as_ref())
+    }
+}
+
+/// For commands and subcommands without options
+impl FromCommandData for () {
+    fn from_options(
+        _options: &[CommandDataOption],
+        _resolved: Option<&InteractionDataResolved>,
+    ) -> Result<Self, CommandError> {
+        Ok(())
+    }
+
+    fn options() -> Vec<CommandOption> {
+        Vec::new()
+    }
+}
+
+/// A type a single command option can be parsed into
+pub trait OptionValue: Sized {
+    const KIND: CommandOptionType;
+
+    fn from_value(
+        value: &CommandOptionValue,
+        resolved: Option<&InteractionDataResolved>,
+    ) -> Option<Self>;
+}
+
+/// Values that can be offered as an option choice
+pub trait ChoiceValue {
+    fn into_choice(self) -> CommandOptionChoiceValue;
+}
+
+impl ChoiceValue for &str {
+    fn into_choice(self) -> CommandOptionChoiceValue {
+        CommandOptionChoiceValue::String(self.to_string())
+    }
+}
+
+impl ChoiceValue for String {
+    fn into_choice(self) -> CommandOptionChoiceValue {
+        CommandOptionChoiceValue::String(self)
+    }
+}
+
+impl ChoiceValue for i64 {
+    fn into_choice(self) -> CommandOptionChoiceValue {
+        CommandOptionChoiceValue::Integer(self)
+    }
+}
+
+impl ChoiceValue for f64 {
+    fn into_choice(self) -> CommandOptionChoiceValue {
+        CommandOptionChoiceValue::Number(self)
+    }
+}
+
+/// Human readable name of an option type for error messages
+fn kind_name(kind: CommandOptionType) -> &'static str {
+    match kind {
+        CommandOptionType::String => "text",
+        CommandOptionType::Integer => "whole number",
+        CommandOptionType::Number => "number",
+        CommandOptionType::Boolean => "true or false",
+        CommandOptionType::User => "user",
+        CommandOptionType::Channel => "channel",
+        CommandOptionType::Role => "role",
+        CommandOptionType::Mentionable => "user or role",
+        CommandOptionType::Attachment => "attachment",
+        _ => "value",
+    }
+}
+
+/// Parse an optional option, `Ok(None)` when it was not given
+pub fn optional<T: OptionValue>(
+    options: &[CommandDataOption],
+    resolved: Option<&InteractionDataResolved>,
+    name: &str,
+) -> Result<Option<T>, CommandError> {
+    let Some(option) = options.iter().find(|option| option.name == name) else {
+        return Ok(None);
+    };
+
+    T::from_value(&option.value, resolved)
+        .map(Some)
+        .ok_or_else(|| {
+            CommandError::Validation(format!("`{}` must be a {}", name, kind_name(T::KIND)))
+        })
+}
+
+/// Parse a required option
+pub fn required<T: OptionValue>(
+    options: &[CommandDataOption],
+    resolved: Option<&InteractionDataResolved>,
+    name: &str,
+) -> Result<T, CommandError> {
+    optional(options, resolved, name)?
+        .ok_or_else(|| CommandError::Validation(format!("`{}` is required", name)))
+}
+
+impl OptionValue for String {
+    const KIND: CommandOptionType = CommandOptionType::String;
+
+    fn from_value(value: &CommandOptionValue, _: Option<&InteractionDataResolved>) -> Option<Self> {
+        match value {
+            CommandOptionValue::String(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl OptionValue for i64 {
+    const KIND: CommandOptionType = CommandOptionType::Integer;
+
+    fn from_value(value: &CommandOptionValue, _: Option<&InteractionDataResolved>) -> Option<Self> {
+        match value {
+            CommandOptionValue::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl OptionValue for f64 {
+    const KIND: CommandOptionType = CommandOptionType::Number;
+
+    fn from_value(value: &CommandOptionValue, _: Option<&InteractionDataResolved>) -> Option<Self> {
+        match value {
+            CommandOptionValue::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl OptionValue for bool {
+    const KIND: CommandOptionType = CommandOptionType::Boolean;
+
+    fn from_value(value: &CommandOptionValue, _: Option<&InteractionDataResolved>) -> Option<Self> {
+        match value {
+            CommandOptionValue::Boolean(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl OptionValue for Id<UserMarker> {
+    const KIND: CommandOptionType = CommandOptionType::User;
+
+    fn from_value(value: &CommandOptionValue, _: Option<&InteractionDataResolved>) -> Option<Self> {
+        match value {
+            CommandOptionValue::User(user_id) => Some(*user_id),
+            _ => None,
+        }
+    }
+}
+
+impl OptionValue for Id<RoleMarker> {
+    const KIND: CommandOptionType = CommandOptionType::Role;
+
+    fn from_value(value: &CommandOptionValue, _: Option<&InteractionDataResolved>) -> Option<Self> {
+        match value {
+            CommandOptionValue::Role(role_id) => Some(*role_id),
+            _ => None,
+        }
+    }
+}
+
+impl OptionValue for Id<ChannelMarker> {
+    const KIND: CommandOptionType = CommandOptionType::Channel;
+
+    fn from_value(value: &CommandOptionValue, _: Option<&InteractionDataResolved>) -> Option<Self> {
+        match value {
+            CommandOptionValue::Channel(channel_id) => Some(*channel_id),
+            _ => None,
+        }
+    }
+}
+
+/// A mentionable option, the id of either a user or a role
+impl OptionValue for Id<GenericMarker> {
+    const KIND: CommandOptionType = CommandOptionType::Mentionable;
+
+    fn from_value(value: &CommandOptionValue, _: Option<&InteractionDataResolved>) -> Option<Self> {
+        match value {
+            CommandOptionValue::Mentionable(id) => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+impl OptionValue for Id<AttachmentMarker> {
+    const KIND: CommandOptionType = CommandOptionType::Attachment;
+
+    fn from_value(value: &CommandOptionValue, _: Option<&InteractionDataResolved>) -> Option<Self> {
+        match value {
+            CommandOptionValue::Attachment(attachment_id) => Some(*attachment_id),
+            _ => None,
+        }
+    }
+}
+
+impl OptionValue for User {
+    const KIND: CommandOptionType = CommandOptionType::User;
+
+    fn from_value(
+        value: &CommandOptionValue,
+        resolved: Option<&InteractionDataResolved>,
+    ) -> Option<Self> {
+        let user_id = Id::<UserMarker>::from_value(value, resolved)?;
+        resolved?.users.get(&user_id).cloned()
+    }
+}
+
+/// The guild member of a user option, only present when the user is in the guild
+impl OptionValue for InteractionMember {
+    const KIND: CommandOptionType = CommandOptionType::User;
+
+    fn from_value(
+        value: &CommandOptionValue,
+        resolved: Option<&InteractionDataResolved>,
+    ) -> Option<Self> {
+        let user_id = Id::<UserMarker>::from_value(value, resolved)?;
+        resolved?.members.get(&user_id).cloned()
+    }
+}
+
+impl OptionValue for Role {
+    const KIND: CommandOptionType = CommandOptionType::Role;
+
+    fn from_value(
+        value: &CommandOptionValue,
+        resolved: Option<&InteractionDataResolved>,
+    ) -> Option<Self> {
+        let role_id = Id::<RoleMarker>::from_value(value, resolved)?;
+        resolved?.roles.get(&role_id).cloned()
+    }
+}
+
+impl OptionValue for InteractionChannel {
+    const KIND: CommandOptionType = CommandOptionType::Channel;
+
+    fn from_value(
+        value: &CommandOptionValue,
+        resolved: Option<&InteractionDataResolved>,
+    ) -> Option<Self> {
+        let channel_id = Id::<ChannelMarker>::from_value(value, resolved)?;
+        resolved?.channels.get(&channel_id).cloned()
+    }
+}
+
+impl OptionValue for Attachment {
+    const KIND: CommandOptionType = CommandOptionType::Attachment;
+
+    fn from_value(
+        value: &CommandOptionValue,
+        resolved: Option<&InteractionDataResolved>,
+    ) -> Option<Self> {
+        let attachment_id = Id::<AttachmentMarker>::from_value(value, resolved)?;
+        resolved?.attachments.get(&attachment_id).cloned()
+    }
+}
@@ -0,0 +1,237 @@
+use super::components::CustomId;
+use super::{CommandContext, CommandError};
+use async_trait::async_trait;
+use std::time::Duration;
+use tracing::warn;
+use twilight_model::application::interaction::Interaction;
+use twilight_model::channel::message::component::{ButtonStyle, SelectMenuType};
+use twilight_model::channel::message::{Component, Embed, MessageFlags};
+use twilight_model::http::interaction::{
+    InteractionResponse, InteractionResponseData, InteractionResponseType,
+};
+use twilight_model::id::Id;
+use twilight_model::id::marker::InteractionMarker;
+use twilight_util::builder::message::{
+    ActionRowBuilder, ButtonBuilder, SelectMenuBuilder, SelectMenuOptionBuilder,
+};
+
+/// Custom id prefix of the paginator components, they are collected and never routed
+const PAGE_PREFIX: &str = "page";
+/// Most options a select menu can have
+const MAX_JUMP_OPTIONS: usize = 25;
+
+/// The pages of a listing, rendered one at a time
+///
+/// Only the page being shown is loaded, so a source can run a query per page instead of
+/// loading the whole listing up front.
+#[async_trait]
+pub trait PageSource: Send + Sync {
+    /// Number of pages, `0` when there is nothing to list
+    async fn page_count(&self) -> Result<usize, CommandError>;
+
+    /// Render a page, `index` starts at `0`
+    async fn page(&self, index: usize, page_count: usize) -> Result<Embed, CommandError>;
+}
+
+/// Shows a `PageSource` with buttons to page through it
+///
+/// Only the member that ran the command can turn pages. The buttons are disabled once nobody
+/// used them for `timeout`.
+pub struct Paginator<S: PageSource> {
+    source: S,
+    timeout: Duration,
+    empty: String,
+    not_yours: String,
+}
+
+impl<S: PageSource> Paginator<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            timeout: Duration::from_secs(120),
+            empty: "There is nothing to show".to_string(),
+            not_yours: "Only the member that ran the command can turn these pages".to_string(),
+        }
+    }
+
+    /// How long the buttons keep working after they were last used
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Shown instead of the paginator when there are no pages
+    pub fn empty(mut self, message: impl Into<String>) -> Self {
+        self.empty = message.into();
+        self
+    }
+
+    /// Shown to members that try to turn someone else's pages
+    pub fn not_yours(mut self, message: impl Into<String>) -> Self {
+        self.not_yours = message.into();
+        self
+    }
+
+    /// Reply with the first page and handle page turns until the paginator expires
+    pub async fn send(
+        self,
+        context: &mut CommandContext,
+        ephemeral: bool,
+    ) -> Result<(), CommandError> {
+        let Some(user_id) = context.get_user_id() else {
+            return Err(CommandError::Internal("Interaction without a user".into()));
+        };
+        let flags = ephemeral.then_some(MessageFlags::EPHEMERAL);
+
+        let page_count = self.source.page_count().await?;
+        if page_count == 0 {
+            return context
+                .respond(InteractionResponse {
+                    kind: InteractionResponseType::ChannelMessageWithSource,
+                    data: Some(InteractionResponseData {
+                        content: Some(self.empty),
+                        flags,
+                        ..Default::default()
+                    }),
+                })
+                .await;
+        }
+
+        let mut index = 0;
+        let embed = self.source.page(index, page_count).await?;
+        if page_count == 1 {
+            return context.reply_embed(embed, ephemeral).await;
+        }
+
+        let owner = context.interaction.id;
+        context
+            .respond(InteractionResponse {
+                kind: InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    embeds: Some(vec![embed]),
+                    components: Some(controls(owner, index, page_count, false)),
+                    flags,
+                    ..Default::default()
+                }),
+            })
+            .await?;
+        let message_id = context.get_response().await?.id;
+
+        // Every page turn brings a fresh token, the latest one is used to disable the buttons
+        let mut latest = context.clone();
+
+        while let Some(mut press) = context
+            .collect_component(message_id, self.timeout, |_: &Interaction| true)
+            .await?
+        {
+            if press.context.get_user_id() != Some(user_id) {
+                if let Err(error) = press.context.reply_ephemeral(self.not_yours.as_str()).await {
+                    warn!("Could not turn away a page turn: {}", error);
+                }
+                continue;
+            }
+
+            let custom_id = CustomId::parse(&press.data.custom_id);
+            index = match custom_id.get::<String>(1)?.as_str() {
+                "first" => 0,
+                "previous" => index.saturating_sub(1),
+                "next" => index + 1,
+                "last" => page_count - 1,
+                "jump" => press
+                    .data
+                    .values
+                    .first()
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(index),
+                _ => index,
+            }
+            .min(page_count - 1);
+
+            let embed = self.source.page(index, page_count).await?;
+            press
+                .context
+                .respond(InteractionResponse {
+                    kind: InteractionResponseType::UpdateMessage,
+                    data: Some(InteractionResponseData {
+                        embeds: Some(vec![embed]),
+                        components: Some(controls(owner, index, page_count, false)),
+                        ..Default::default()
+                    }),
+                })
+                .await?;
+            latest = press.context;
+        }
+
+        let components = controls(owner, index, page_count, true);
+        if let Err(error) = latest
+            .update(None, None, None, Some(&components), &[], &[])
+            .await
+        {
+            warn!("Could not disable expired paginator: {}", error);
+        }
+
+        Ok(())
+    }
+}
+
+/// Buttons to turn pages and, for longer listings, a menu to jump to a page
+fn controls(
+    owner: Id<InteractionMarker>,
+    index: usize,
+    page_count: usize,
+    disabled: bool,
+) -> Vec<Component> {
+    let custom_id = |action: &str| {
+        CustomId::new(PAGE_PREFIX)
+            .arg(owner)
+            .arg(action)
+            .to_string()
+    };
+    let button = |action: &str, label: String, unavailable: bool| {
+        ButtonBuilder::new(ButtonStyle::Secondary)
+            .label(label)
+            .custom_id(custom_id(action))
+            .disabled(disabled || unavailable)
+            .build()
+    };
+
+    let last = page_count - 1;
+    let mut rows = vec![Component::ActionRow(
+        ActionRowBuilder::new()
+            .component(button("first", "«".to_string(), index == 0))
+            .component(button("previous", "‹".to_string(), index == 0))
+            .component(button(
+                "current",
+                format!("{} / {}", index + 1, page_count),
+                true,
+            ))
+            .component(button("next", "›".to_string(), index == last))
+            .component(button("last", "»".to_string(), index == last))
+            .build(),
+    )];
+
+    if page_count > 2 {
+        // Center the options on the current page, a menu can't list every page of a long listing
+        let start = index
+            .saturating_sub(MAX_JUMP_OPTIONS / 2)
+            .min(page_count.saturating_sub(MAX_JUMP_OPTIONS));
+        let end = (start + MAX_JUMP_OPTIONS).min(page_count);
+
+        let mut menu = SelectMenuBuilder::new(custom_id("jump"), SelectMenuType::Text)
+            .placeholder("Jump to page")
+            .disabled(disabled);
+        for page in start..end {
+            menu = menu.option(
+                SelectMenuOptionBuilder::new(format!("Page {}", page + 1), page.to_string())
+                    .default(page == index)
+                    .build(),
+            );
+        }
+
+        rows.push(Component::ActionRow(
+            ActionRowBuilder::new().component(menu.build()).build(),
+        ));
+    }
+
+    rows
+}
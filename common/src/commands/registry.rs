@@ -1,19 +1,111 @@
-use super::{CommandBundle, CommandContext, CommandError, CommandRegistration, CommandScope};
+use super::autocomplete::{FocusedOption, valid_choices};
+use super::components::{ComponentHandler, CustomId, ModalHandler};
+use super::definition::{
+    CommandDefinitionError, MAX_CHAT_INPUT_COMMANDS, MAX_CONTEXT_MENU_COMMANDS,
+    deployed_definition, validate_command,
+};
+use super::deploy::{self, CommandChange, DeployOptions, DeployTarget};
+use super::errors::{ErrorReportTarget, report_error};
+use super::middleware::{Middleware, Next, Target, default_layers};
+use super::{CommandBundle, CommandContext, CommandRegistration, CommandScope};
 use crate::context::Context;
 use crate::handler::Handler;
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
+use twilight_model::application::command::{Command as ApplicationCommand, CommandType};
 use twilight_model::application::interaction::application_command::CommandData;
+use twilight_model::application::interaction::message_component::MessageComponentInteractionData;
+use twilight_model::application::interaction::modal::ModalInteractionData;
 use twilight_model::application::interaction::{Interaction, InteractionData, InteractionType};
 use twilight_model::gateway::event::Event;
 use twilight_model::id::{Id, marker::GuildMarker};
 
+/// A registration that was rejected before anything was sent to Discord
+#[derive(Debug)]
+pub enum RegistrationError {
+    Invalid {
+        handler: String,
+        error: CommandDefinitionError,
+    },
+    /// Two commands with the same name in the same place
+    DuplicateCommand {
+        name: String,
+        target: DeployTarget,
+        first: String,
+        second: String,
+    },
+    /// Two component or modal handlers for the same custom id prefix
+    DuplicatePrefix {
+        kind: &'static str,
+        prefix: String,
+        first: String,
+        second: String,
+    },
+    TooManyCommands {
+        target: DeployTarget,
+        kind: CommandType,
+        count: usize,
+        max: usize,
+    },
+}
+
+impl std::fmt::Display for RegistrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistrationError::Invalid { handler, error } => {
+                write!(f, "{} registered an invalid command: {}", handler, error)
+            }
+            RegistrationError::DuplicateCommand {
+                name,
+                target,
+                first,
+                second,
+            } => write!(
+                f,
+                "/{} ({}) is registered by both {} and {}",
+                name, target, first, second
+            ),
+            RegistrationError::DuplicatePrefix {
+                kind,
+                prefix,
+                first,
+                second,
+            } => write!(
+                f,
+                "{} prefix `{}` is registered by both {} and {}",
+                kind, prefix, first, second
+            ),
+            RegistrationError::TooManyCommands {
+                target,
+                kind,
+                count,
+                max,
+            } => write!(
+                f,
+                "{} has {} {:?} commands, at most {} are allowed",
+                target, count, kind, max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RegistrationError {}
+
 /// Registry for managing Discord commands
 pub struct CommandRegistry {
     global_commands: HashMap<Box<str>, Box<dyn CommandBundle>>,
     guild_commands: HashMap<Id<GuildMarker>, HashMap<Arc<str>, Arc<dyn CommandBundle>>>,
+    components: HashMap<Box<str>, Box<dyn ComponentHandler>>,
+    modals: HashMap<Box<str>, Box<dyn ModalHandler>>,
+    /// The handler that registered each command, component prefix and modal prefix
+    owners: HashMap<(DeployTarget, String), String>,
+    prefix_owners: HashMap<(&'static str, String), String>,
+    /// Run around every command, component and modal, outermost first
+    layers: Vec<Box<dyn Middleware>>,
+    /// Where failures are reported besides the logs
+    report_errors_to: Option<ErrorReportTarget>,
 }
 
 impl CommandRegistry {
@@ -21,22 +113,158 @@ impl CommandRegistry {
         Self {
             global_commands: HashMap::new(),
             guild_commands: HashMap::new(),
+            components: HashMap::new(),
+            modals: HashMap::new(),
+            owners: HashMap::new(),
+            prefix_owners: HashMap::new(),
+            layers: default_layers(),
+            report_errors_to: None,
         }
     }
 
-    /// Register commands from registrations
-    pub fn register_commands(&mut self, registrations: Vec<CommandRegistration>) {
+    /// Send failures with their reference id to the owner or a channel
+    pub fn report_errors_to(&mut self, target: ErrorReportTarget) {
+        self.report_errors_to = Some(target);
+    }
+
+    /// Add layers inside the ones already registered
+    pub fn add_layers(&mut self, layers: Vec<Box<dyn Middleware>>) {
+        self.layers.extend(layers);
+    }
+
+    /// Run a target through the layers and report what went wrong
+    async fn run(&self, mut command_context: CommandContext, target: Target<'_>) {
+        if let Err(error) = Next::new(&self.layers, &target)
+            .run(&mut command_context)
+            .await
+        {
+            report_error(&mut command_context, &target, error, self.report_errors_to).await;
+        }
+    }
+
+    /// Remember who owns a prefix, or the error if another handler got there first
+    fn claim_prefix(
+        &mut self,
+        kind: &'static str,
+        prefix: &str,
+        handler: &str,
+    ) -> Result<(), RegistrationError> {
+        match self.prefix_owners.get(&(kind, prefix.to_string())) {
+            Some(first) => Err(RegistrationError::DuplicatePrefix {
+                kind,
+                prefix: prefix.to_string(),
+                first: first.clone(),
+                second: handler.to_string(),
+            }),
+            None => {
+                self.prefix_owners
+                    .insert((kind, prefix.to_string()), handler.to_string());
+                Ok(())
+            }
+        }
+    }
+
+    /// Register a handler's component handlers by their custom id prefix
+    pub fn register_components(
+        &mut self,
+        handler: &str,
+        components: Vec<Box<dyn ComponentHandler>>,
+    ) -> Vec<RegistrationError> {
+        let mut errors = Vec::new();
+
+        for component in components {
+            let prefix = component.prefix().to_string();
+            if let Err(error) = self.claim_prefix("Component", &prefix, handler) {
+                errors.push(error);
+                continue;
+            }
+
+            info!("Registering component handler: {}", prefix);
+            self.components.insert(prefix.into(), component);
+        }
+
+        errors
+    }
+
+    /// Register a handler's modal handlers by their custom id prefix
+    pub fn register_modals(
+        &mut self,
+        handler: &str,
+        modals: Vec<Box<dyn ModalHandler>>,
+    ) -> Vec<RegistrationError> {
+        let mut errors = Vec::new();
+
+        for modal in modals {
+            let prefix = modal.prefix().to_string();
+            if let Err(error) = self.claim_prefix("Modal", &prefix, handler) {
+                errors.push(error);
+                continue;
+            }
+
+            info!("Registering modal handler: {}", prefix);
+            self.modals.insert(prefix.into(), modal);
+        }
+
+        errors
+    }
+
+    /// Register a handler's commands, rejecting invalid ones and names that are already taken
+    pub fn register_commands(
+        &mut self,
+        handler: &str,
+        registrations: Vec<CommandRegistration>,
+    ) -> Vec<RegistrationError> {
+        let mut errors = Vec::new();
+
         for registration in registrations {
             let command = registration.command;
+            let name = command.name();
+
+            if let Err(error) = validate_command(&deployed_definition(command.as_ref())) {
+                errors.push(RegistrationError::Invalid {
+                    handler: handler.to_string(),
+                    error,
+                });
+                continue;
+            }
+
+            let targets: Vec<DeployTarget> = match &registration.scope {
+                CommandScope::Global => vec![DeployTarget::Global],
+                CommandScope::Guild(guild_id) => vec![DeployTarget::Guild(*guild_id)],
+                CommandScope::Guilds(guild_ids) => guild_ids
+                    .iter()
+                    .map(|guild_id| DeployTarget::Guild(*guild_id))
+                    .collect(),
+            };
+
+            let conflicts: Vec<_> = targets
+                .iter()
+                .filter_map(|target| {
+                    let first = self.owners.get(&(*target, name.clone()))?;
+                    Some(RegistrationError::DuplicateCommand {
+                        name: name.clone(),
+                        target: *target,
+                        first: first.clone(),
+                        second: handler.to_string(),
+                    })
+                })
+                .collect();
+            if !conflicts.is_empty() {
+                errors.extend(conflicts);
+                continue;
+            }
+
+            for target in targets {
+                self.owners
+                    .insert((target, name.clone()), handler.to_string());
+            }
 
             match registration.scope {
                 CommandScope::Global => {
-                    let name = command.name();
                     info!("Registering global command: {}", name);
                     self.global_commands.insert(name.into(), command);
                 }
                 CommandScope::Guild(guild_id) => {
-                    let name = command.name();
                     info!("Registering guild command for {}: {}", guild_id, name);
                     self.guild_commands
                         .entry(guild_id)
@@ -44,7 +272,7 @@ impl CommandRegistry {
                         .insert(name.into(), Arc::from(command));
                 }
                 CommandScope::Guilds(guild_ids) => {
-                    let name: Arc<str> = command.name().into();
+                    let name: Arc<str> = name.into();
                     let command: Arc<dyn CommandBundle> = command.into();
                     for guild_id in guild_ids {
                         info!("Registering guild command for {}: {}", guild_id, name);
@@ -56,40 +284,154 @@ impl CommandRegistry {
                 }
             }
         }
+
+        errors
     }
 
-    /// Deploy all commands to Discord
-    pub async fn deploy(&self, context: &Context) -> Result<(), Box<dyn std::error::Error>> {
-        // Deploy global commands
-        if !self.global_commands.is_empty() {
-            let definitions: Vec<_> = self
-                .global_commands
+    /// Check the number of commands of each type against Discord's limits
+    pub fn check_limits(&self) -> Vec<RegistrationError> {
+        let mut targets = vec![(
+            DeployTarget::Global,
+            self.global_commands
                 .values()
-                .map(|cmd| cmd.definition())
-                .collect();
+                .map(|command| command.definition().kind)
+                .collect::<Vec<_>>(),
+        )];
+        for (guild_id, commands) in &self.guild_commands {
+            targets.push((
+                DeployTarget::Guild(*guild_id),
+                commands
+                    .values()
+                    .map(|command| command.definition().kind)
+                    .collect(),
+            ));
+        }
 
-            info!("Deploying {} global commands", definitions.len());
-            context
-                .client
-                .interaction(context.application_id)
-                .set_global_commands(&definitions)
-                .await?;
+        let limits = [
+            (CommandType::ChatInput, MAX_CHAT_INPUT_COMMANDS),
+            (CommandType::User, MAX_CONTEXT_MENU_COMMANDS),
+            (CommandType::Message, MAX_CONTEXT_MENU_COMMANDS),
+        ];
+
+        let mut errors = Vec::new();
+        for (target, kinds) in targets {
+            for (kind, max) in limits {
+                let count = kinds
+                    .iter()
+                    .filter(|command_kind| **command_kind == kind)
+                    .count();
+                if count > max {
+                    errors.push(RegistrationError::TooManyCommands {
+                        target,
+                        kind,
+                        count,
+                        max,
+                    });
+                }
+            }
         }
 
-        // Deploy guild commands
+        errors
+    }
+
+    /// Bring the commands on Discord in line with the registered ones
+    ///
+    /// Only commands that were added, changed or removed are sent. Targets are deployed one by
+    /// one and a target that fails, e.g. a guild that removed the `applications.commands` scope,
+    /// doesn't stop the others. Old commands are only looked for in guilds that have guild
+    /// commands registered and dev guilds, former dev guilds only lose their dev copies.
+    pub async fn deploy(
+        &self,
+        context: &Context,
+        options: &DeployOptions,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut targets: HashMap<DeployTarget, Vec<ApplicationCommand>> = HashMap::new();
+
         for (guild_id, commands) in &self.guild_commands {
-            let definitions: Vec<_> = commands.values().map(|cmd| cmd.definition()).collect();
+            targets.insert(
+                DeployTarget::Guild(*guild_id),
+                commands
+                    .values()
+                    .map(|cmd| deployed_definition(cmd.as_ref()))
+                    .collect(),
+            );
+        }
+
+        let global: Vec<_> = self
+            .global_commands
+            .values()
+            .map(|cmd| deployed_definition(cmd.as_ref()))
+            .collect();
 
+        if options.dev_guilds.is_empty() {
+            targets.insert(DeployTarget::Global, global);
+        } else {
             info!(
-                "Deploying {} commands to guild {}",
-                definitions.len(),
-                guild_id
+                "Deploying global commands to dev guilds {:?} instead",
+                options.dev_guilds
             );
-            context
-                .client
-                .interaction(context.application_id)
-                .set_guild_commands(*guild_id, &definitions)
-                .await?;
+
+            for guild_id in &options.dev_guilds {
+                let definitions = targets.entry(DeployTarget::Guild(*guild_id)).or_default();
+
+                for command in &global {
+                    // Commands registered for the guild itself take precedence
+                    if definitions.iter().any(|existing| {
+                        existing.name == command.name && existing.kind == command.kind
+                    }) {
+                        warn!(
+                            "/{} is registered for dev guild {}, not deploying the global one",
+                            command.name, guild_id
+                        );
+                        continue;
+                    }
+
+                    definitions.push(deploy::dev_definition(command));
+                }
+            }
+        }
+
+        // Always in the same order, global commands first
+        let mut targets: Vec<_> = targets.into_iter().collect();
+        targets.sort_by_key(|(target, _)| match target {
+            DeployTarget::Global => 0,
+            DeployTarget::Guild(guild_id) => guild_id.get(),
+        });
+
+        // Guilds with commands of their own already lose their dev copies as unregistered commands
+        let mut former_dev_guilds: Vec<_> = options
+            .former_dev_guilds
+            .iter()
+            .filter(|guild_id| {
+                !targets
+                    .iter()
+                    .any(|(target, _)| *target == DeployTarget::Guild(**guild_id))
+            })
+            .copied()
+            .collect();
+        former_dev_guilds.sort();
+        former_dev_guilds.dedup();
+
+        let mut failed = 0;
+        for (target, definitions) in &targets {
+            if let Err(error) = deploy_target(context, *target, definitions, options).await {
+                error!("Could not deploy commands for {}: {}", target, error);
+                failed += 1;
+            }
+        }
+        for guild_id in &former_dev_guilds {
+            if let Err(error) = remove_dev_leftovers(context, *guild_id, options).await {
+                error!(
+                    "Could not remove dev commands from former dev guild {}: {}",
+                    guild_id, error
+                );
+                failed += 1;
+            }
+        }
+
+        if failed > 0 {
+            let total = targets.len() + former_dev_guilds.len();
+            return Err(format!("{} of {} targets failed", failed, total).into());
         }
 
         Ok(())
@@ -130,60 +472,113 @@ impl CommandRegistry {
                     self.handle_autocomplete(command_ctx, data).await;
                 }
             }
+            InteractionType::MessageComponent => {
+                if let Some(InteractionData::MessageComponent(data)) = &interaction.data {
+                    self.handle_component(command_ctx, data).await;
+                }
+            }
+            InteractionType::ModalSubmit => {
+                if let Some(InteractionData::ModalSubmit(data)) = &interaction.data {
+                    self.handle_modal(command_ctx, data).await;
+                }
+            }
             _ => {}
         }
     }
 
-    async fn handle_command(
+    async fn handle_component(
         &self,
-        mut command_context: CommandContext,
-        data: &twilight_model::application::interaction::application_command::CommandData,
+        command_context: CommandContext,
+        data: &MessageComponentInteractionData,
     ) {
-        if let Some(command) = self.find_command(&data.name, data.guild_id) {
-            if let Err(error) = command.execute(&mut command_context, data).await {
-                error!("Command execution error: {}", error);
-
-                match error {
-                    CommandError::Http(message) => {
-                        let message = format!("HTTP error: {}", message);
+        let custom_id = CustomId::parse(&data.custom_id);
 
-                        if command_context.reply_ephemeral(&message).await.is_err() {
-                            error!("Command reply error: {}", message);
-                        }
-                    }
-                    CommandError::Validation(message) => {
-                        let message = format!("Command validation error: {}", message);
+        if let Some(handler) = self.components.get(custom_id.prefix()) {
+            let target = Target::Component {
+                handler: handler.as_ref(),
+                custom_id: &custom_id,
+                data,
+            };
+            self.run(command_context, target).await;
+        }
+    }
 
-                        if command_context.reply_ephemeral(&message).await.is_err() {
-                            error!("Command validation error: {}", message);
-                        }
-                    }
-                    CommandError::Internal(message) => {
-                        error!("Command internal error: {}", message);
+    async fn handle_modal(&self, command_context: CommandContext, data: &ModalInteractionData) {
+        let custom_id = CustomId::parse(&data.custom_id);
 
-                        if command_context.reply_ephemeral(&message).await.is_err() {
-                            error!("Command internal error: {}", message);
-                        }
-                    }
-                }
-            }
+        if let Some(handler) = self.modals.get(custom_id.prefix()) {
+            let target = Target::Modal {
+                handler: handler.as_ref(),
+                custom_id: &custom_id,
+                data,
+            };
+            self.run(command_context, target).await;
         }
     }
 
-    async fn handle_autocomplete(&self, ctx: CommandContext, data: &CommandData) {
+    async fn handle_command(&self, command_context: CommandContext, data: &CommandData) {
         if let Some(command) = self.find_command(&data.name, data.guild_id) {
-            match command.autocomplete(ctx, data).await {
-                Ok(_choices) => {
-                    // Response is handled by the command's autocomplete method
-                }
-                Err(error) => {
-                    error!("Autocomplete error: {}", error);
-                }
+            self.run(command_context, Target::Command { command, data })
+                .await;
+        }
+    }
+
+    async fn handle_autocomplete(&self, mut ctx: CommandContext, data: &CommandData) {
+        let Some(command) = self.find_command(&data.name, data.guild_id) else {
+            return;
+        };
+
+        // Don't offer suggestions to members that can't run the command
+        if command.access().check(&ctx).is_err() {
+            return;
+        }
+
+        let Some(focused) = FocusedOption::find(&data.options) else {
+            warn!("Autocomplete for {} without a focused option", data.name);
+            return;
+        };
+
+        let choices = match command.autocomplete(&ctx, data, &focused).await {
+            Ok(choices) => valid_choices(choices, &focused),
+            Err(error) => {
+                error!("Autocomplete error: {}", error);
+                Vec::new()
             }
+        };
+
+        if let Err(error) = ctx.autocomplete(choices).await {
+            error!("Could not send autocomplete choices: {}", error);
         }
     }
 }
 
+/// Fetch, plan and apply the changes for one target
+async fn deploy_target(
+    context: &Context,
+    target: DeployTarget,
+    definitions: &[ApplicationCommand],
+    options: &DeployOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let existing = deploy::fetch(context, target).await?;
+    let changes = deploy::plan(definitions, &existing);
+    deploy::apply(context, target, &changes, options).await
+}
+
+/// Delete only the dev copies of global commands from a guild, nothing else there is ours
+async fn remove_dev_leftovers(
+    context: &Context,
+    guild_id: Id<GuildMarker>,
+    options: &DeployOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let target = DeployTarget::Guild(guild_id);
+    let existing = deploy::fetch(context, target).await?;
+    let changes: Vec<_> = deploy::plan(&[], &existing)
+        .into_iter()
+        .filter(|change| matches!(change, CommandChange::Delete { dev: true, .. }))
+        .collect();
+    deploy::apply(context, target, &changes, options).await
+}
+
 #[async_trait]
 impl Handler for CommandRegistry {
     async fn handle(&self, ctx: Arc<Context>, event: Arc<Event>) {
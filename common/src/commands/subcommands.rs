@@ -0,0 +1,307 @@
+use super::access::CommandAccess;
+use super::autocomplete::FocusedOption;
+use super::cooldowns::Cooldown;
+use super::options::FromCommandData;
+use super::{AutocompleteChoice, CommandBundle, CommandContext, CommandError};
+use async_trait::async_trait;
+use twilight_model::application::command::{
+    Command as ApplicationCommand, CommandOption, CommandOptionType, CommandType,
+};
+use twilight_model::application::interaction::InteractionDataResolved;
+use twilight_model::application::interaction::application_command::{
+    CommandData, CommandDataOption, CommandOptionValue,
+};
+use twilight_util::builder::command::CommandBuilder;
+
+/// A leaf of a subcommand tree, e.g. `link` in `/admin link`
+#[async_trait]
+pub trait Subcommand: Send + Sync {
+    /// The options of this leaf, parsed before `execute` is called
+    type Options: FromCommandData + Send;
+
+    fn name(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    async fn execute(
+        &self,
+        context: &mut CommandContext,
+        options: Self::Options,
+    ) -> Result<(), CommandError>;
+
+    /// Suggest values for the focused option of this leaf (optional)
+    async fn autocomplete(
+        &self,
+        _context: &CommandContext,
+        _options: &[CommandDataOption],
+        _focused: &FocusedOption,
+    ) -> Result<Vec<AutocompleteChoice>, CommandError> {
+        Ok(Vec::new())
+    }
+}
+
+/// `Subcommand` without its options type, so leaves with different options fit in one tree
+#[async_trait]
+trait Leaf: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn definition(&self) -> CommandOption;
+
+    async fn execute(
+        &self,
+        context: &mut CommandContext,
+        options: &[CommandDataOption],
+        resolved: Option<&InteractionDataResolved>,
+    ) -> Result<(), CommandError>;
+
+    async fn autocomplete(
+        &self,
+        context: &CommandContext,
+        options: &[CommandDataOption],
+        focused: &FocusedOption,
+    ) -> Result<Vec<AutocompleteChoice>, CommandError>;
+}
+
+#[async_trait]
+impl<S: Subcommand> Leaf for S {
+    fn name(&self) -> &str {
+        Subcommand::name(self)
+    }
+
+    fn definition(&self) -> CommandOption {
+        subcommand_option(
+            CommandOptionType::SubCommand,
+            Subcommand::name(self),
+            self.description(),
+            S::Options::options(),
+        )
+    }
+
+    async fn execute(
+        &self,
+        context: &mut CommandContext,
+        options: &[CommandDataOption],
+        resolved: Option<&InteractionDataResolved>,
+    ) -> Result<(), CommandError> {
+        let options = S::Options::from_options(options, resolved)?;
+        Subcommand::execute(self, context, options).await
+    }
+
+    async fn autocomplete(
+        &self,
+        context: &CommandContext,
+        options: &[CommandDataOption],
+        focused: &FocusedOption,
+    ) -> Result<Vec<AutocompleteChoice>, CommandError> {
+        Subcommand::autocomplete(self, context, options, focused).await
+    }
+}
+
+fn subcommand_option(
+    kind: CommandOptionType,
+    name: &str,
+    description: &str,
+    options: Vec<CommandOption>,
+) -> CommandOption {
+    CommandOption {
+        autocomplete: None,
+        channel_types: None,
+        choices: None,
+        description: description.to_string(),
+        description_localizations: None,
+        kind,
+        max_length: None,
+        max_value: None,
+        min_length: None,
+        min_value: None,
+        name: name.to_string(),
+        name_localizations: None,
+        options: Some(options),
+        required: None,
+    }
+}
+
+/// A group of subcommands, e.g. `ban` in `/admin ban add`
+pub struct SubcommandGroup {
+    name: String,
+    description: String,
+    leaves: Vec<Box<dyn Leaf>>,
+}
+
+impl SubcommandGroup {
+    pub fn new(name: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            leaves: Vec::new(),
+        }
+    }
+
+    pub fn subcommand(mut self, subcommand: impl Subcommand + 'static) -> Self {
+        self.leaves.push(Box::new(subcommand));
+        self
+    }
+
+    fn find(&self, name: &str) -> Option<&dyn Leaf> {
+        self.leaves
+            .iter()
+            .find(|leaf| leaf.name() == name)
+            .map(|leaf| leaf.as_ref())
+    }
+}
+
+enum Node {
+    Leaf(Box<dyn Leaf>),
+    Group(SubcommandGroup),
+}
+
+/// A command made of subcommands and subcommand groups with one handler per leaf
+///
+/// ```ignore
+/// SubcommandTree::new("admin", "Manage Embark ID links")
+///     .subcommand(LinkSubcommand::new(database))
+///     .group(SubcommandGroup::new("ban", "Manage bans").subcommand(BanAddSubcommand))
+/// ```
+pub struct SubcommandTree {
+    name: String,
+    description: String,
+    nodes: Vec<Node>,
+    access: CommandAccess,
+    cooldown: Option<Cooldown>,
+}
+
+impl SubcommandTree {
+    pub fn new(name: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            nodes: Vec::new(),
+            access: CommandAccess::default(),
+            cooldown: None,
+        }
+    }
+
+    pub fn subcommand(mut self, subcommand: impl Subcommand + 'static) -> Self {
+        self.nodes.push(Node::Leaf(Box::new(subcommand)));
+        self
+    }
+
+    pub fn group(mut self, group: SubcommandGroup) -> Self {
+        self.nodes.push(Node::Group(group));
+        self
+    }
+
+    /// Who may use the command, Discord only supports this for the whole tree
+    pub fn access(mut self, access: CommandAccess) -> Self {
+        self.access = access;
+        self
+    }
+
+    /// Limit how often any subcommand of the tree can be used, they share one bucket
+    pub fn cooldown(mut self, cooldown: Cooldown) -> Self {
+        self.cooldown = Some(cooldown);
+        self
+    }
+
+    /// Walk the options down to the invoked leaf and the options that belong to it
+    fn resolve<'a>(
+        &self,
+        options: &'a [CommandDataOption],
+    ) -> Result<(&dyn Leaf, &'a [CommandDataOption]), CommandError> {
+        let unknown = || CommandError::Validation("Unknown subcommand".into());
+
+        let Some(option) = options.first() else {
+            return Err(CommandError::Validation("Missing subcommand".into()));
+        };
+
+        match &option.value {
+            CommandOptionValue::SubCommand(leaf_options) => self
+                .nodes
+                .iter()
+                .find_map(|node| match node {
+                    Node::Leaf(leaf) if leaf.name() == option.name => Some(leaf.as_ref()),
+                    _ => None,
+                })
+                .map(|leaf| (leaf, leaf_options.as_slice()))
+                .ok_or_else(unknown),
+            CommandOptionValue::SubCommandGroup(group_options) => {
+                let group = self
+                    .nodes
+                    .iter()
+                    .find_map(|node| match node {
+                        Node::Group(group) if group.name == option.name => Some(group),
+                        _ => None,
+                    })
+                    .ok_or_else(unknown)?;
+
+                let Some(leaf_option) = group_options.first() else {
+                    return Err(CommandError::Validation("Missing subcommand".into()));
+                };
+                let CommandOptionValue::SubCommand(leaf_options) = &leaf_option.value else {
+                    return Err(unknown());
+                };
+
+                group
+                    .find(&leaf_option.name)
+                    .map(|leaf| (leaf, leaf_options.as_slice()))
+                    .ok_or_else(unknown)
+            }
+            _ => Err(unknown()),
+        }
+    }
+}
+
+#[async_trait]
+impl CommandBundle for SubcommandTree {
+    fn definition(&self) -> ApplicationCommand {
+        let mut command =
+            CommandBuilder::new(&self.name, &self.description, CommandType::ChatInput).build();
+
+        command.options = self
+            .nodes
+            .iter()
+            .map(|node| match node {
+                Node::Leaf(leaf) => leaf.definition(),
+                Node::Group(group) => subcommand_option(
+                    CommandOptionType::SubCommandGroup,
+                    &group.name,
+                    &group.description,
+                    group.leaves.iter().map(|leaf| leaf.definition()).collect(),
+                ),
+            })
+            .collect();
+
+        command
+    }
+
+    async fn execute(
+        &self,
+        context: &mut CommandContext,
+        data: &CommandData,
+    ) -> Result<(), CommandError> {
+        let (leaf, options) = self.resolve(&data.options)?;
+        leaf.execute(context, options, data.resolved.as_ref()).await
+    }
+
+    async fn autocomplete(
+        &self,
+        context: &CommandContext,
+        data: &CommandData,
+        focused: &FocusedOption,
+    ) -> Result<Vec<AutocompleteChoice>, CommandError> {
+        let (leaf, options) = self.resolve(&data.options)?;
+        leaf.autocomplete(context, options, focused).await
+    }
+
+    fn access(&self) -> CommandAccess {
+        self.access.clone()
+    }
+
+    fn cooldown(&self) -> Option<Cooldown> {
+        self.cooldown
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+}
@@ -0,0 +1,152 @@
+use super::{Locale, LocalizedText};
+use std::collections::{BTreeSet, HashMap};
+
+/// An error in a translation file
+#[derive(Debug)]
+pub struct TranslationError {
+    pub locale: Locale,
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for TranslationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} line {}: {}",
+            self.locale.as_str(),
+            self.line,
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for TranslationError {}
+
+/// Messages for every locale a module ships a translation file for
+///
+/// Files use a small subset of Fluent (`.ftl`): `key = value` lines, `#` comments and indented
+/// continuation lines, which are joined with a newline. Placeholders are written as `{name}`.
+#[derive(Debug, Clone)]
+pub struct Translations {
+    default: Locale,
+    messages: HashMap<Locale, HashMap<String, String>>,
+}
+
+impl Translations {
+    /// `default` is the locale every other locale is checked against and falls back to
+    pub fn new(default: Locale) -> Self {
+        Self {
+            default,
+            messages: HashMap::new(),
+        }
+    }
+
+    /// Add the messages of a translation file, usually loaded with `include_str!`
+    pub fn with_source(mut self, locale: Locale, source: &str) -> Result<Self, TranslationError> {
+        let messages = self.messages.entry(locale).or_default();
+        let mut last_key: Option<String> = None;
+
+        for (index, line) in source.lines().enumerate() {
+            let error = |message: &str| TranslationError {
+                locale,
+                line: index + 1,
+                message: message.to_string(),
+            };
+
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with(char::is_whitespace) {
+                let Some(value) = last_key.as_ref().and_then(|key| messages.get_mut(key)) else {
+                    return Err(error("continuation line without a message"));
+                };
+                if !value.is_empty() {
+                    value.push('\n');
+                }
+                value.push_str(trimmed);
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(error("expected `key = value`"));
+            };
+
+            let key = key.trim();
+            if key.is_empty()
+                || !key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                return Err(error("invalid message key"));
+            }
+            if messages.contains_key(key) {
+                return Err(error("duplicate message key"));
+            }
+
+            messages.insert(key.to_string(), value.trim().to_string());
+            last_key = Some(key.to_string());
+        }
+
+        Ok(self)
+    }
+
+    pub fn default_locale(&self) -> Locale {
+        self.default
+    }
+
+    /// Locales that have a translation file
+    pub fn locales(&self) -> impl Iterator<Item = &Locale> {
+        self.messages.keys()
+    }
+
+    /// A message in a locale, following its fallback chain and then the default locale
+    pub fn get(&self, key: &str, locale: Option<&Locale>) -> Option<&str> {
+        locale
+            .into_iter()
+            .flat_map(Locale::fallback_chain)
+            .chain(std::iter::once(self.default))
+            .find_map(|locale| self.messages.get(&locale)?.get(key))
+            .map(String::as_str)
+    }
+
+    /// A message in every locale, for places that take a `LocalizedText`
+    pub fn localized(&self, key: &str) -> Option<LocalizedText> {
+        let mut text = LocalizedText::new(self.messages.get(&self.default)?.get(key)?);
+
+        for (locale, messages) in &self.messages {
+            if let Some(message) = messages.get(key) {
+                text = text.with_localization(*locale, message);
+            }
+        }
+
+        Some(text)
+    }
+
+    /// Like `localized`, but a missing message shows its key instead of failing
+    pub fn text(&self, key: &str) -> LocalizedText {
+        self.localized(key)
+            .unwrap_or_else(|| LocalizedText::new(key))
+    }
+
+    /// Keys of the default locale that another declared locale does not translate
+    pub fn missing_keys(&self) -> Vec<(Locale, String)> {
+        let Some(default_messages) = self.messages.get(&self.default) else {
+            return Vec::new();
+        };
+        let keys: BTreeSet<&String> = default_messages.keys().collect();
+
+        let mut missing = Vec::new();
+        for (locale, messages) in &self.messages {
+            for key in &keys {
+                if !messages.contains_key(*key) {
+                    missing.push((*locale, key.to_string()));
+                }
+            }
+        }
+
+        missing
+    }
+}
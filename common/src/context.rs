@@ -4,12 +4,14 @@ use derive_getters::Getters;
 use twilight_cache_inmemory::InMemoryCache;
 use twilight_cache_inmemory::model::CachedMember;
 use twilight_http::Client;
+use twilight_model::channel::message::Component;
 use twilight_model::guild::Permissions;
 use twilight_model::id::Id;
 use twilight_model::id::marker::{
     ApplicationMarker, ChannelMarker, GuildMarker, MessageMarker, UserMarker,
 };
 use twilight_model::user::User;
+use twilight_standby::Standby;
 use twilight_util::permission_calculator::PermissionCalculator;
 
 #[derive(Getters)]
@@ -18,6 +20,8 @@ pub struct Context {
     pub cache: InMemoryCache,
     pub application_id: Id<ApplicationMarker>,
     pub bot: User,
+    /// Lets commands wait for follow-up events such as a button press, fed by `Bot::start`
+    pub standby: Standby,
 }
 
 impl Context {
@@ -32,6 +36,7 @@ impl Context {
             cache,
             application_id,
             bot,
+            standby: Standby::new(),
         }
     }
 
@@ -63,6 +68,16 @@ impl Context {
         &self,
         user_id: Id<UserMarker>,
         content: &str,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.send_dm_with_components(user_id, content, &[]).await
+    }
+
+    /// Send a DM with components such as buttons
+    pub async fn send_dm_with_components(
+        &self,
+        user_id: Id<UserMarker>,
+        content: &str,
+        components: &[Component],
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
         let dm_channel = self
             .client
@@ -74,6 +89,7 @@ impl Context {
         self.client
             .create_message(dm_channel.id)
             .content(content)
+            .components(components)
             .await?;
 
         Ok(())
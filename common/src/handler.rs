@@ -1,4 +1,6 @@
 use crate::commands::CommandRegistration;
+use crate::commands::components::{ComponentHandler, ModalHandler};
+use crate::commands::middleware::Middleware;
 use crate::context::Context;
 use async_trait::async_trait;
 use std::sync::Arc;
@@ -8,8 +10,28 @@ use twilight_gateway::Event;
 pub trait Handler: Send + Sync {
     async fn handle(&self, context: Arc<Context>, event: Arc<Event>);
 
+    /// Name used when reporting registration problems, the type name by default
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
     /// Return commands this handler wants to register
     fn commands(&self) -> Vec<CommandRegistration> {
         Vec::new()
     }
+
+    /// Return button and select menu handlers, routed by custom id prefix
+    fn components(&self) -> Vec<Box<dyn ComponentHandler>> {
+        Vec::new()
+    }
+
+    /// Return modal handlers, routed by custom id prefix
+    fn modals(&self) -> Vec<Box<dyn ModalHandler>> {
+        Vec::new()
+    }
+
+    /// Return layers to run around every command, component and modal, after the built-in ones
+    fn middleware(&self) -> Vec<Box<dyn Middleware>> {
+        Vec::new()
+    }
 }
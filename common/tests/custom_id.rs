@@ -0,0 +1,51 @@
+use common::commands::components::{CustomId, MAX_CUSTOM_ID_LENGTH};
+
+#[test]
+fn arguments_round_trip() {
+    for arg in ["plain", "a:b", "50%", "%3A", "%25", "%:%3A:", ":", ""] {
+        let custom_id = CustomId::new("prefix").arg(arg).arg(42);
+        let parsed = CustomId::parse(&custom_id.to_string());
+
+        assert_eq!(parsed, custom_id, "{:?} did not survive a round trip", arg);
+        assert_eq!(parsed.prefix(), "prefix");
+        assert_eq!(parsed.get::<String>(0).unwrap(), arg);
+        assert_eq!(parsed.get::<u64>(1).unwrap(), 42);
+    }
+}
+
+#[test]
+fn separators_in_arguments_are_escaped() {
+    assert_eq!(
+        CustomId::new("prefix").arg("a:b").arg("100%").to_string(),
+        "prefix:a%3Ab:100%25"
+    );
+}
+
+#[test]
+fn missing_and_invalid_arguments_are_errors() {
+    let parsed = CustomId::parse("prefix:abc");
+
+    assert!(parsed.get::<u64>(0).is_err());
+    assert!(parsed.get::<String>(1).is_err());
+    assert_eq!(CustomId::parse("prefix").prefix(), "prefix");
+}
+
+#[test]
+fn build_enforces_the_length_limit() {
+    // `p:` and the argument
+    let longest = "x".repeat(MAX_CUSTOM_ID_LENGTH - 2);
+    assert_eq!(
+        CustomId::new("p").arg(&longest).build().unwrap().len(),
+        MAX_CUSTOM_ID_LENGTH
+    );
+    assert!(
+        CustomId::new("p")
+            .arg(format!("{}x", longest))
+            .build()
+            .is_err()
+    );
+
+    // Escaping counts, every `:` takes three characters
+    assert!(CustomId::new("p").arg(":".repeat(32)).build().is_ok());
+    assert!(CustomId::new("p").arg(":".repeat(33)).build().is_err());
+}
@@ -0,0 +1,109 @@
+use common::commands::deploy::{CommandChange, DEV_MARKER, plan};
+use twilight_model::application::command::{Command, CommandType};
+use twilight_model::application::interaction::InteractionContextType;
+use twilight_model::id::Id;
+use twilight_model::oauth::ApplicationIntegrationType;
+use twilight_util::builder::command::{CommandBuilder, StringBuilder};
+
+fn local(name: &str, description: &str) -> Command {
+    CommandBuilder::new(name, description, CommandType::ChatInput)
+        .option(StringBuilder::new("text", "Some text").required(false))
+        .build()
+}
+
+/// A command as Discord returns it, with the fields it fills in itself
+fn remote(command: &Command, id: u64) -> Command {
+    let mut command = command.clone();
+    command.id = Some(Id::new(id));
+    command.application_id = Some(Id::new(1));
+    command.version = Id::new(id + 1000);
+    command.nsfw = Some(false);
+    command.contexts = Some(vec![
+        InteractionContextType::PrivateChannel,
+        InteractionContextType::Guild,
+        InteractionContextType::BotDm,
+    ]);
+    command.integration_types = Some(vec![ApplicationIntegrationType::GuildInstall]);
+    command
+}
+
+#[test]
+fn unchanged_commands_are_left_alone() {
+    let commands = [local("ping", "Check the bot"), local("echo", "Repeat text")];
+    let existing = [remote(&commands[1], 11), remote(&commands[0], 10)];
+
+    let changes = plan(&commands, &existing);
+
+    assert!(changes.is_empty(), "unexpected changes: {:?}", changes);
+}
+
+#[test]
+fn changed_commands_are_updated() {
+    let existing = [remote(&local("ping", "Check the bot"), 10)];
+
+    let changes = plan(&[local("ping", "Check if the bot is up")], &existing);
+
+    match changes.as_slice() {
+        [CommandChange::Update { id, fields, .. }] => {
+            assert_eq!(*id, Id::new(10));
+            assert_eq!(fields, &["description"]);
+        }
+        other => panic!("expected one update, got {:?}", other),
+    }
+}
+
+#[test]
+fn narrower_contexts_are_a_change() {
+    let existing = [remote(&local("setup", "Set up the bot"), 10)];
+    let mut command = local("setup", "Set up the bot");
+    command.contexts = Some(vec![InteractionContextType::Guild]);
+
+    let changes = plan(&[command], &existing);
+
+    match changes.as_slice() {
+        [CommandChange::Update { fields, .. }] => assert_eq!(fields, &["contexts"]),
+        other => panic!("expected one update, got {:?}", other),
+    }
+}
+
+#[test]
+fn new_commands_are_created() {
+    let changes = plan(&[local("ping", "Check the bot")], &[]);
+
+    assert!(
+        matches!(changes.as_slice(), [CommandChange::Create(command)] if command.name == "ping")
+    );
+}
+
+#[test]
+fn removed_commands_are_deleted() {
+    let existing = [
+        remote(&local("ping", "Check the bot"), 10),
+        remote(&local("old", "No longer registered"), 11),
+        remote(
+            &local("dev", &format!("{} Only while developing", DEV_MARKER)),
+            12,
+        ),
+    ];
+
+    let changes = plan(&[local("ping", "Check the bot")], &existing);
+
+    match changes.as_slice() {
+        [
+            CommandChange::Delete {
+                id: dev_id,
+                name: dev_name,
+                dev: true,
+            },
+            CommandChange::Delete {
+                id: old_id,
+                name: old_name,
+                dev: false,
+            },
+        ] => {
+            assert_eq!((*dev_id, dev_name.as_str()), (Id::new(12), "dev"));
+            assert_eq!((*old_id, old_name.as_str()), (Id::new(11), "old"));
+        }
+        other => panic!("expected two deletions, got {:?}", other),
+    }
+}
@@ -0,0 +1,250 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Expr, Field, Fields, GenericArgument, LitStr, PathArguments, Token, Type,
+    parenthesized, parse_macro_input,
+};
+
+/// Derives `common::commands::options::FromCommandData`
+///
+/// Field attributes, all inside `#[option(..)]`:
+/// - `description = ".."`, defaults to the field's doc comment
+/// - `rename = ".."`, the option name, defaults to the field name
+/// - `min_value = ..` / `max_value = ..` for integer options
+/// - `choice("Label", value)`, repeatable
+/// - `choices = ..`, an iterator of `(label, value)` pairs
+/// - `autocomplete`
+#[proc_macro_derive(FromCommandData, attributes(option))]
+pub fn derive_from_command_data(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+struct OptionField {
+    ident: syn::Ident,
+    name: String,
+    description: String,
+    inner: Type,
+    required: bool,
+    min_value: Option<Expr>,
+    max_value: Option<Expr>,
+    choices: Vec<(LitStr, Expr)>,
+    choice_list: Option<Expr>,
+    autocomplete: bool,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "FromCommandData can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "FromCommandData needs named fields",
+        ));
+    };
+
+    let fields = fields
+        .named
+        .iter()
+        .map(parse_field)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let parsers = fields.iter().map(|field| {
+        let OptionField {
+            ident,
+            name,
+            inner,
+            required,
+            ..
+        } = field;
+        let parse = if *required {
+            quote!(required)
+        } else {
+            quote!(optional)
+        };
+
+        quote! {
+            #ident: ::common::commands::options::#parse::<#inner>(options, resolved, #name)?
+        }
+    });
+
+    let definitions = fields.iter().map(|field| {
+        let OptionField {
+            name,
+            description,
+            inner,
+            required,
+            ..
+        } = field;
+
+        let min_value = field
+            .min_value
+            .as_ref()
+            .map(|value| quote!(.min_value(#value)));
+        let max_value = field
+            .max_value
+            .as_ref()
+            .map(|value| quote!(.max_value(#value)));
+        let autocomplete = field.autocomplete.then(|| quote!(.autocomplete(true)));
+        let choice_list = field
+            .choice_list
+            .as_ref()
+            .map(|choices| quote!(.choices(#choices)));
+        let choices = field.choices.iter().map(|(label, value)| {
+            quote! {
+                .choice(
+                    ::common::commands::LocalizedText::new(#label),
+                    ::common::commands::options::ChoiceValue::into_choice(#value),
+                )
+            }
+        });
+
+        quote! {
+            ::common::commands::definition::LocalizedOptionBuilder::new(
+                <#inner as ::common::commands::options::OptionValue>::KIND,
+                ::common::commands::LocalizedText::new(#name),
+                ::common::commands::LocalizedText::new(#description),
+            )
+            .required(#required)
+            #min_value
+            #max_value
+            #autocomplete
+            #(#choices)*
+            #choice_list
+            .build()
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::common::commands::options::FromCommandData for #ident #type_generics #where_clause {
+            fn from_options(
+                options: &[::twilight_model::application::interaction::application_command::CommandDataOption],
+                resolved: ::std::option::Option<&::twilight_model::application::interaction::InteractionDataResolved>,
+            ) -> ::std::result::Result<Self, ::common::commands::CommandError> {
+                ::std::result::Result::Ok(Self {
+                    #(#parsers,)*
+                })
+            }
+
+            fn options() -> ::std::vec::Vec<::twilight_model::application::command::CommandOption> {
+                ::std::vec![#(#definitions),*]
+            }
+        }
+    })
+}
+
+fn parse_field(field: &Field) -> syn::Result<OptionField> {
+    let ident = field
+        .ident
+        .clone()
+        .ok_or_else(|| syn::Error::new_spanned(field, "expected a named field"))?;
+
+    let (inner, required) = match option_inner(&field.ty) {
+        Some(inner) => (inner.clone(), false),
+        None => (field.ty.clone(), true),
+    };
+
+    let mut option = OptionField {
+        name: ident.to_string(),
+        description: doc_comment(field),
+        ident,
+        inner,
+        required,
+        min_value: None,
+        max_value: None,
+        choices: Vec::new(),
+        choice_list: None,
+        autocomplete: false,
+    };
+
+    for attribute in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("option"))
+    {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("description") {
+                option.description = meta.value()?.parse::<LitStr>()?.value();
+            } else if meta.path.is_ident("rename") {
+                option.name = meta.value()?.parse::<LitStr>()?.value();
+            } else if meta.path.is_ident("min_value") {
+                option.min_value = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("max_value") {
+                option.max_value = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("autocomplete") {
+                option.autocomplete = true;
+            } else if meta.path.is_ident("choices") {
+                option.choice_list = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("choice") {
+                let content;
+                parenthesized!(content in meta.input);
+                let label: LitStr = content.parse()?;
+                content.parse::<Token![,]>()?;
+                let value: Expr = content.parse()?;
+                option.choices.push((label, value));
+            } else {
+                return Err(meta.error("unknown option attribute"));
+            }
+            Ok(())
+        })?;
+    }
+
+    if option.description.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &option.ident,
+            "options need a description, add a doc comment or #[option(description = \"..\")]",
+        ));
+    }
+
+    Ok(option)
+}
+
+/// `T` for an `Option<T>` field
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+
+    match arguments.args.first()? {
+        GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
+
+fn doc_comment(field: &Field) -> String {
+    field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(name_value) => match &name_value.value {
+                Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(doc),
+                    ..
+                }) => Some(doc.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -1,8 +1,11 @@
-use rusqlite::{Connection, Result as SqliteResult, params};
+use rusqlite::{Connection, Result as SqliteResult, Row, params, params_from_iter};
 use std::path::Path;
 use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::{error::Error, rc::Rc};
+use tracing::{error, warn};
 
+use twilight_model::channel::message::Component;
 use twilight_model::id::{
     Id,
     marker::{ChannelMarker, GuildMarker, MessageMarker, RoleMarker, UserMarker},
@@ -15,11 +18,401 @@ pub struct GuildSettings {
     pub verification_message: Id<MessageMarker>,
 }
 
+pub struct NicknameLockSettings {
+    pub guild_id: Id<GuildMarker>,
+    pub enabled: bool,
+    /// Seconds a changed nickname is tolerated before it is restored
+    pub grace_period: u64,
+}
+
+/// What happens when the verified role and the link of a member disagree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RolePolicy {
+    /// Give the role back to linked members, record unlinked holders as overrides
+    Readd,
+    /// Accept every manual change and record it as an override
+    RecordOverride,
+    /// Give the role back to linked members and strip it from unlinked holders
+    Strict,
+}
+
+impl RolePolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RolePolicy::Readd => "readd",
+            RolePolicy::RecordOverride => "override",
+            RolePolicy::Strict => "strict",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "readd" => Some(RolePolicy::Readd),
+            "override" => Some(RolePolicy::RecordOverride),
+            "strict" => Some(RolePolicy::Strict),
+            _ => None,
+        }
+    }
+}
+
+/// An entry of the link audit trail
+#[derive(Debug, Clone)]
+pub struct LinkAuditEntry {
+    pub guild_id: Option<Id<GuildMarker>>,
+    pub discord_user: Id<UserMarker>,
+    pub action: String,
+    pub detail: String,
+    /// Unix timestamp in seconds
+    pub created_at: u64,
+}
+
+impl LinkAuditEntry {
+    pub fn new(
+        guild_id: Option<Id<GuildMarker>>,
+        discord_user: Id<UserMarker>,
+        action: impl Into<String>,
+        detail: impl Into<String>,
+    ) -> Self {
+        LinkAuditEntry {
+            guild_id,
+            discord_user,
+            action: action.into(),
+            detail: detail.into(),
+            created_at: unix_now(),
+        }
+    }
+}
+
+/// Whose link audit trail to read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditScope {
+    User(Id<UserMarker>),
+    Guild(Id<GuildMarker>),
+    /// A user's entries from one guild only
+    UserInGuild(Id<UserMarker>, Id<GuildMarker>),
+}
+
+impl AuditScope {
+    fn condition(&self) -> (&'static str, Vec<i64>) {
+        match self {
+            AuditScope::User(discord_user) => ("discord_user = ?", vec![discord_user.get() as i64]),
+            AuditScope::Guild(guild_id) => ("guild_id = ?", vec![guild_id.get() as i64]),
+            AuditScope::UserInGuild(discord_user, guild_id) => (
+                "discord_user = ? AND guild_id = ?",
+                vec![discord_user.get() as i64, guild_id.get() as i64],
+            ),
+        }
+    }
+}
+
+/// A Discord side effect waiting in the outbox
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobKind {
+    AddRole {
+        guild_id: Id<GuildMarker>,
+        discord_user: Id<UserMarker>,
+        role_id: Id<RoleMarker>,
+    },
+    RemoveRole {
+        guild_id: Id<GuildMarker>,
+        discord_user: Id<UserMarker>,
+        role_id: Id<RoleMarker>,
+    },
+    SetNickname {
+        guild_id: Id<GuildMarker>,
+        discord_user: Id<UserMarker>,
+        nickname: Option<String>,
+    },
+    SendDm {
+        /// The guild the DM is about, if any
+        guild_id: Option<Id<GuildMarker>>,
+        discord_user: Id<UserMarker>,
+        content: String,
+    },
+    /// A DM with buttons, such as the consent prompt
+    SendDmWithComponents {
+        guild_id: Option<Id<GuildMarker>>,
+        discord_user: Id<UserMarker>,
+        content: String,
+        components: Vec<Component>,
+    },
+}
+
+impl JobKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobKind::AddRole { .. } => "add_role",
+            JobKind::RemoveRole { .. } => "remove_role",
+            JobKind::SetNickname { .. } => "set_nickname",
+            JobKind::SendDm { .. } => "send_dm",
+            JobKind::SendDmWithComponents { .. } => "send_dm_components",
+        }
+    }
+
+    pub fn guild_id(&self) -> Option<Id<GuildMarker>> {
+        match self {
+            JobKind::AddRole { guild_id, .. }
+            | JobKind::RemoveRole { guild_id, .. }
+            | JobKind::SetNickname { guild_id, .. } => Some(*guild_id),
+            JobKind::SendDm { guild_id, .. } | JobKind::SendDmWithComponents { guild_id, .. } => {
+                *guild_id
+            }
+        }
+    }
+
+    pub fn discord_user(&self) -> Id<UserMarker> {
+        match self {
+            JobKind::AddRole { discord_user, .. }
+            | JobKind::RemoveRole { discord_user, .. }
+            | JobKind::SetNickname { discord_user, .. }
+            | JobKind::SendDm { discord_user, .. }
+            | JobKind::SendDmWithComponents { discord_user, .. } => *discord_user,
+        }
+    }
+
+    /// The role id column
+    fn target(&self) -> Option<i64> {
+        match self {
+            JobKind::AddRole { role_id, .. } | JobKind::RemoveRole { role_id, .. } => {
+                Some(role_id.get() as i64)
+            }
+            _ => None,
+        }
+    }
+
+    /// The nickname or DM content column
+    fn payload(&self) -> Option<&str> {
+        match self {
+            JobKind::SetNickname { nickname, .. } => nickname.as_deref(),
+            JobKind::SendDm { content, .. } | JobKind::SendDmWithComponents { content, .. } => {
+                Some(content)
+            }
+            _ => None,
+        }
+    }
+
+    /// The components column, as JSON
+    fn components(&self) -> Option<String> {
+        match self {
+            JobKind::SendDmWithComponents { components, .. } => {
+                serde_json::to_string(components).ok()
+            }
+            _ => None,
+        }
+    }
+
+    fn from_columns(
+        kind: &str,
+        guild_id: Option<i64>,
+        discord_user: i64,
+        target: Option<i64>,
+        payload: Option<String>,
+        components: Option<String>,
+    ) -> Option<Self> {
+        let guild_id = guild_id.and_then(|guild_id| Id::new_checked(guild_id as u64));
+        let discord_user = Id::new_checked(discord_user as u64)?;
+        let role_id = target.and_then(|role_id| Id::new_checked(role_id as u64));
+
+        match kind {
+            "add_role" => Some(JobKind::AddRole {
+                guild_id: guild_id?,
+                discord_user,
+                role_id: role_id?,
+            }),
+            "remove_role" => Some(JobKind::RemoveRole {
+                guild_id: guild_id?,
+                discord_user,
+                role_id: role_id?,
+            }),
+            "set_nickname" => Some(JobKind::SetNickname {
+                guild_id: guild_id?,
+                discord_user,
+                nickname: payload,
+            }),
+            "send_dm" => Some(JobKind::SendDm {
+                guild_id,
+                discord_user,
+                content: payload?,
+            }),
+            "send_dm_components" => Some(JobKind::SendDmWithComponents {
+                guild_id,
+                discord_user,
+                content: payload?,
+                components: serde_json::from_str(&components?).ok()?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: i64,
+    pub kind: JobKind,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JobQueueDepth {
+    pub pending: u64,
+    pub failed: u64,
+}
+
+/// What to do when a member can not be DMed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DmFallbackPolicy {
+    /// Mention them in the verification channel with a message that is deleted later
+    Mention,
+    /// Mention them in a daily digest in the verification channel
+    Digest,
+    Nothing,
+}
+
+impl DmFallbackPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DmFallbackPolicy::Mention => "mention",
+            DmFallbackPolicy::Digest => "digest",
+            DmFallbackPolicy::Nothing => "nothing",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "mention" => Some(DmFallbackPolicy::Mention),
+            "digest" => Some(DmFallbackPolicy::Digest),
+            "nothing" => Some(DmFallbackPolicy::Nothing),
+            _ => None,
+        }
+    }
+}
+
+pub struct DmFallbackSettings {
+    pub guild_id: Id<GuildMarker>,
+    pub policy: DmFallbackPolicy,
+    /// Minutes before a fallback mention is deleted again
+    pub delete_after: u64,
+}
+
+/// The way a user was last reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryChannel {
+    Dm,
+    Mention,
+    Digest,
+}
+
+impl DeliveryChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryChannel::Dm => "dm",
+            DeliveryChannel::Mention => "mention",
+            DeliveryChannel::Digest => "digest",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dm" => Some(DeliveryChannel::Dm),
+            "mention" => Some(DeliveryChannel::Mention),
+            "digest" => Some(DeliveryChannel::Digest),
+            _ => None,
+        }
+    }
+}
+
 pub struct User {
     pub discord_user: Id<UserMarker>,
     pub embark_id: EmbarkID,
 }
 
+/// Whether the bot is allowed to set a user's nickname to their Embark ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NicknamePreference {
+    Always,
+    Never,
+    /// Decided guild by guild, see [`Database::set_guild_nickname_preference`]
+    PerGuild,
+}
+
+impl NicknamePreference {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NicknamePreference::Always => "always",
+            NicknamePreference::Never => "never",
+            NicknamePreference::PerGuild => "per_guild",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "always" => Some(NicknamePreference::Always),
+            "never" => Some(NicknamePreference::Never),
+            "per_guild" => Some(NicknamePreference::PerGuild),
+            _ => None,
+        }
+    }
+}
+
+/// Who is allowed to look up a user's Embark ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbarkIDVisibility {
+    Everyone,
+    StaffOnly,
+    Nobody,
+}
+
+impl EmbarkIDVisibility {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmbarkIDVisibility::Everyone => "everyone",
+            EmbarkIDVisibility::StaffOnly => "staff",
+            EmbarkIDVisibility::Nobody => "nobody",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "everyone" => Some(EmbarkIDVisibility::Everyone),
+            "staff" => Some(EmbarkIDVisibility::StaffOnly),
+            "nobody" => Some(EmbarkIDVisibility::Nobody),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PrivacySettings {
+    pub discord_user: Id<UserMarker>,
+    pub nickname: NicknamePreference,
+    pub visibility: EmbarkIDVisibility,
+}
+
+impl PrivacySettings {
+    /// Settings used for users that never ran `/privacy`
+    pub fn default_for(discord_user: Id<UserMarker>) -> Self {
+        PrivacySettings {
+            discord_user,
+            nickname: NicknamePreference::Always,
+            visibility: EmbarkIDVisibility::Everyone,
+        }
+    }
+
+    /// Users can always see their own Embark ID
+    pub fn visible_to(&self, viewer: Id<UserMarker>, viewer_is_staff: bool) -> bool {
+        if viewer == self.discord_user {
+            return true;
+        }
+
+        match self.visibility {
+            EmbarkIDVisibility::Everyone => true,
+            EmbarkIDVisibility::StaffOnly => viewer_is_staff,
+            EmbarkIDVisibility::Nobody => false,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct EmbarkID {
     username: Box<str>, // min 2 char max 16 char
@@ -98,77 +491,318 @@ impl Database {
             [],
         )?;
 
-        Ok(Database {
-            conn: Mutex::new(conn),
-        })
-    }
-
-    pub fn get_guild_settings(&self, guild_id: &Id<GuildMarker>) -> Option<GuildSettings> {
-        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS privacy_settings (
+                discord_user INTEGER PRIMARY KEY,
+                nickname TEXT NOT NULL,
+                visibility TEXT NOT NULL
+            );
+            "#,
+            [],
+        )?;
 
-        let mut stmt = conn
-            .prepare(
-                "SELECT guild_id, verification_channel, verified_role, verification_message 
-             FROM guild_settings WHERE guild_id = ?",
-            )
-            .ok()?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS guild_nickname_preferences (
+                discord_user INTEGER NOT NULL,
+                guild_id INTEGER NOT NULL,
+                allowed INTEGER NOT NULL,
+                PRIMARY KEY(discord_user, guild_id)
+            );
+            "#,
+            [],
+        )?;
 
-        let mut rows = stmt.query(params![guild_id.get() as i64]).ok()?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS guild_consent_settings (
+                guild_id INTEGER PRIMARY KEY,
+                require_consent INTEGER NOT NULL
+            );
+            "#,
+            [],
+        )?;
 
-        match rows.next().ok()? {
-            Some(row) => Some(GuildSettings {
-                guild_id: Id::new(row.get(0).ok()?),
-                verification_channel: Id::new(row.get(1).ok()?),
-                verified_role: Id::new(row.get(2).ok()?),
-                verification_message: Id::new(row.get(3).ok()?),
-            }),
-            None => None,
-        }
-    }
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS user_consent_settings (
+                discord_user INTEGER PRIMARY KEY,
+                require_consent INTEGER NOT NULL
+            );
+            "#,
+            [],
+        )?;
 
-    pub fn set_guild_settings(&self, settings: &GuildSettings) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS user_guild_consents (
+                discord_user INTEGER NOT NULL,
+                guild_id INTEGER NOT NULL,
+                PRIMARY KEY(discord_user, guild_id)
+            );
+            "#,
+            [],
+        )?;
 
         conn.execute(
-            "INSERT OR REPLACE INTO guild_settings 
-             (guild_id, verification_channel, verified_role, verification_message) 
-             VALUES (?, ?, ?, ?)",
-            params![
-                settings.guild_id.get() as i64,
-                settings.verification_channel.get() as i64,
-                settings.verified_role.get() as i64,
-                settings.verification_message.get() as i64
-            ],
+            r#"
+            CREATE TABLE IF NOT EXISTS guild_log_channels (
+                guild_id INTEGER PRIMARY KEY,
+                channel_id INTEGER NOT NULL
+            );
+            "#,
+            [],
         )?;
 
-        Ok(())
-    }
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS nickname_lock_settings (
+                guild_id INTEGER PRIMARY KEY,
+                enabled INTEGER NOT NULL,
+                grace_period INTEGER NOT NULL
+            );
+            "#,
+            [],
+        )?;
 
-    pub fn get_user_by_discord_id(&self, discord_user: Id<UserMarker>) -> Option<User> {
-        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS nickname_lock_exempt_roles (
+                guild_id INTEGER NOT NULL,
+                role_id INTEGER NOT NULL,
+                PRIMARY KEY(guild_id, role_id)
+            );
+            "#,
+            [],
+        )?;
 
-        let mut stmt = conn
-            .prepare("SELECT discord_user, embark_id FROM users WHERE discord_user = ?")
-            .ok()?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS nickname_offences (
+                guild_id INTEGER NOT NULL,
+                discord_user INTEGER NOT NULL,
+                count INTEGER NOT NULL,
+                PRIMARY KEY(guild_id, discord_user)
+            );
+            "#,
+            [],
+        )?;
 
-        let mut rows = stmt.query(params![discord_user.get() as i64]).ok()?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS role_policies (
+                guild_id INTEGER PRIMARY KEY,
+                policy TEXT NOT NULL
+            );
+            "#,
+            [],
+        )?;
 
-        match rows.next().ok()? {
-            Some(row) => {
-                let embark_id_str: String = row.get(1).ok()?;
-                Some(User {
-                    discord_user: Id::new(row.get(0).ok()?),
-                    embark_id: EmbarkID::new(&embark_id_str).expect("Database should be correct"),
-                })
-            }
-            None => None,
-        }
-    }
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS role_overrides (
+                guild_id INTEGER NOT NULL,
+                discord_user INTEGER NOT NULL,
+                has_role INTEGER NOT NULL,
+                PRIMARY KEY(guild_id, discord_user)
+            );
+            "#,
+            [],
+        )?;
 
-    pub fn get_user_by_embark_id(&self, embark_id: &EmbarkID) -> Option<User> {
-        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS link_audit (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                guild_id INTEGER,
+                discord_user INTEGER NOT NULL,
+                action TEXT NOT NULL,
+                detail TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+            "#,
+            [],
+        )?;
 
-        let mut stmt = conn
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS log_categories (
+                guild_id INTEGER NOT NULL,
+                category TEXT NOT NULL,
+                enabled INTEGER NOT NULL,
+                PRIMARY KEY(guild_id, category)
+            );
+            "#,
+            [],
+        )?;
+
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS jobs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind TEXT NOT NULL,
+                guild_id INTEGER,
+                discord_user INTEGER NOT NULL,
+                target INTEGER,
+                payload TEXT,
+                components TEXT,
+                status TEXT NOT NULL DEFAULT 'pending',
+                attempts INTEGER NOT NULL DEFAULT 0,
+                next_attempt_at INTEGER NOT NULL,
+                last_error TEXT
+            );
+            "#,
+            [],
+        )?;
+
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS dm_fallback_settings (
+                guild_id INTEGER PRIMARY KEY,
+                policy TEXT NOT NULL,
+                delete_after INTEGER NOT NULL
+            );
+            "#,
+            [],
+        )?;
+
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS dm_delivery (
+                discord_user INTEGER PRIMARY KEY,
+                channel TEXT NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+            "#,
+            [],
+        )?;
+
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS dm_digest (
+                guild_id INTEGER NOT NULL,
+                discord_user INTEGER NOT NULL,
+                PRIMARY KEY(guild_id, discord_user)
+            );
+            "#,
+            [],
+        )?;
+
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS dm_digest_runs (
+                guild_id INTEGER PRIMARY KEY,
+                posted_at INTEGER NOT NULL
+            );
+            "#,
+            [],
+        )?;
+
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS scheduled_deletions (
+                message_id INTEGER PRIMARY KEY,
+                channel_id INTEGER NOT NULL,
+                delete_at INTEGER NOT NULL
+            );
+            "#,
+            [],
+        )?;
+
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS dev_guilds (
+                guild_id INTEGER PRIMARY KEY
+            );
+            "#,
+            [],
+        )?;
+
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS guild_messages (
+                guild_id INTEGER NOT NULL,
+                message_id TEXT NOT NULL,
+                locale TEXT NOT NULL,
+                content TEXT NOT NULL,
+                PRIMARY KEY(guild_id, message_id, locale)
+            );
+            "#,
+            [],
+        )?;
+
+        Ok(Database {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub fn get_guild_settings(&self, guild_id: &Id<GuildMarker>) -> Option<GuildSettings> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT guild_id, verification_channel, verified_role, verification_message 
+             FROM guild_settings WHERE guild_id = ?",
+            )
+            .ok()?;
+
+        let mut rows = stmt.query(params![guild_id.get() as i64]).ok()?;
+
+        match rows.next().ok()? {
+            Some(row) => Some(GuildSettings {
+                guild_id: Id::new(row.get(0).ok()?),
+                verification_channel: Id::new(row.get(1).ok()?),
+                verified_role: Id::new(row.get(2).ok()?),
+                verification_message: Id::new(row.get(3).ok()?),
+            }),
+            None => None,
+        }
+    }
+
+    pub fn set_guild_settings(&self, settings: &GuildSettings) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO guild_settings 
+             (guild_id, verification_channel, verified_role, verification_message) 
+             VALUES (?, ?, ?, ?)",
+            params![
+                settings.guild_id.get() as i64,
+                settings.verification_channel.get() as i64,
+                settings.verified_role.get() as i64,
+                settings.verification_message.get() as i64
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn get_user_by_discord_id(&self, discord_user: Id<UserMarker>) -> Option<User> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn
+            .prepare("SELECT discord_user, embark_id FROM users WHERE discord_user = ?")
+            .ok()?;
+
+        let mut rows = stmt.query(params![discord_user.get() as i64]).ok()?;
+
+        match rows.next().ok()? {
+            Some(row) => {
+                let embark_id_str: String = row.get(1).ok()?;
+                Some(User {
+                    discord_user: Id::new(row.get(0).ok()?),
+                    embark_id: EmbarkID::new(&embark_id_str).expect("Database should be correct"),
+                })
+            }
+            None => None,
+        }
+    }
+
+    pub fn get_user_by_embark_id(&self, embark_id: &EmbarkID) -> Option<User> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn
             .prepare("SELECT discord_user, embark_id FROM users WHERE embark_id = ?")
             .ok()?;
 
@@ -222,4 +856,993 @@ impl Database {
 
         Ok(())
     }
+
+    pub fn get_privacy_settings(&self, discord_user: Id<UserMarker>) -> PrivacySettings {
+        let conn = self.conn.lock().unwrap();
+
+        let settings = conn
+            .query_row(
+                "SELECT nickname, visibility FROM privacy_settings WHERE discord_user = ?",
+                params![discord_user.get() as i64],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )
+            .ok();
+
+        let mut privacy_settings = PrivacySettings::default_for(discord_user);
+
+        if let Some((nickname, visibility)) = settings {
+            if let Some(nickname) = NicknamePreference::from_name(&nickname) {
+                privacy_settings.nickname = nickname;
+            }
+            if let Some(visibility) = EmbarkIDVisibility::from_name(&visibility) {
+                privacy_settings.visibility = visibility;
+            }
+        }
+
+        privacy_settings
+    }
+
+    pub fn set_privacy_settings(&self, settings: &PrivacySettings) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO privacy_settings (discord_user, nickname, visibility) 
+             VALUES (?, ?, ?)",
+            params![
+                settings.discord_user.get() as i64,
+                settings.nickname.as_str(),
+                settings.visibility.as_str()
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn get_guild_nickname_preference(
+        &self,
+        discord_user: Id<UserMarker>,
+        guild_id: Id<GuildMarker>,
+    ) -> Option<bool> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.query_row(
+            "SELECT allowed FROM guild_nickname_preferences WHERE discord_user = ? AND guild_id = ?",
+            params![discord_user.get() as i64, guild_id.get() as i64],
+            |row| row.get(0),
+        )
+        .ok()
+    }
+
+    pub fn set_guild_nickname_preference(
+        &self,
+        discord_user: Id<UserMarker>,
+        guild_id: Id<GuildMarker>,
+        allowed: bool,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO guild_nickname_preferences (discord_user, guild_id, allowed) 
+             VALUES (?, ?, ?)",
+            params![discord_user.get() as i64, guild_id.get() as i64, allowed],
+        )?;
+
+        Ok(())
+    }
+
+    /// Whether the bot may set the user's nickname to their Embark ID in this guild
+    pub fn should_set_nickname(
+        &self,
+        discord_user: Id<UserMarker>,
+        guild_id: Id<GuildMarker>,
+    ) -> bool {
+        match self.get_privacy_settings(discord_user).nickname {
+            NicknamePreference::Always => true,
+            NicknamePreference::Never => false,
+            // Opt-in: only guilds the user explicitly allowed
+            NicknamePreference::PerGuild => self
+                .get_guild_nickname_preference(discord_user, guild_id)
+                .unwrap_or(false),
+        }
+    }
+
+    pub fn get_guild_requires_consent(&self, guild_id: Id<GuildMarker>) -> bool {
+        let conn = self.conn.lock().unwrap();
+
+        conn.query_row(
+            "SELECT require_consent FROM guild_consent_settings WHERE guild_id = ?",
+            params![guild_id.get() as i64],
+            |row| row.get(0),
+        )
+        .unwrap_or(false)
+    }
+
+    pub fn set_guild_requires_consent(
+        &self,
+        guild_id: Id<GuildMarker>,
+        require_consent: bool,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO guild_consent_settings (guild_id, require_consent) VALUES (?, ?)",
+            params![guild_id.get() as i64, require_consent],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn get_user_requires_consent(&self, discord_user: Id<UserMarker>) -> bool {
+        let conn = self.conn.lock().unwrap();
+
+        conn.query_row(
+            "SELECT require_consent FROM user_consent_settings WHERE discord_user = ?",
+            params![discord_user.get() as i64],
+            |row| row.get(0),
+        )
+        .unwrap_or(false)
+    }
+
+    pub fn set_user_requires_consent(
+        &self,
+        discord_user: Id<UserMarker>,
+        require_consent: bool,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO user_consent_settings (discord_user, require_consent) VALUES (?, ?)",
+            params![discord_user.get() as i64, require_consent],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn has_consented(&self, discord_user: Id<UserMarker>, guild_id: Id<GuildMarker>) -> bool {
+        let conn = self.conn.lock().unwrap();
+
+        conn.query_row(
+            "SELECT 1 FROM user_guild_consents WHERE discord_user = ? AND guild_id = ?",
+            params![discord_user.get() as i64, guild_id.get() as i64],
+            |_| Ok(()),
+        )
+        .is_ok()
+    }
+
+    pub fn add_consent(
+        &self,
+        discord_user: Id<UserMarker>,
+        guild_id: Id<GuildMarker>,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR IGNORE INTO user_guild_consents (discord_user, guild_id) VALUES (?, ?)",
+            params![discord_user.get() as i64, guild_id.get() as i64],
+        )?;
+
+        Ok(())
+    }
+
+    /// Whether an existing link may only be applied in this guild after the user agreed to it
+    pub fn needs_consent(&self, discord_user: Id<UserMarker>, guild_id: Id<GuildMarker>) -> bool {
+        let consent_required = self.get_guild_requires_consent(guild_id)
+            || self.get_user_requires_consent(discord_user);
+
+        consent_required && !self.has_consented(discord_user, guild_id)
+    }
+
+    pub fn get_log_channel(&self, guild_id: Id<GuildMarker>) -> Option<Id<ChannelMarker>> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.query_row(
+            "SELECT channel_id FROM guild_log_channels WHERE guild_id = ?",
+            params![guild_id.get() as i64],
+            |row| row.get::<_, i64>(0),
+        )
+        .ok()
+        .and_then(|channel_id| Id::new_checked(channel_id as u64))
+    }
+
+    pub fn set_log_channel(
+        &self,
+        guild_id: Id<GuildMarker>,
+        channel_id: Id<ChannelMarker>,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO guild_log_channels (guild_id, channel_id) VALUES (?, ?)",
+            params![guild_id.get() as i64, channel_id.get() as i64],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn get_nickname_lock_settings(&self, guild_id: Id<GuildMarker>) -> NicknameLockSettings {
+        let conn = self.conn.lock().unwrap();
+
+        let settings = conn
+            .query_row(
+                "SELECT enabled, grace_period FROM nickname_lock_settings WHERE guild_id = ?",
+                params![guild_id.get() as i64],
+                |row| Ok((row.get::<_, bool>(0)?, row.get::<_, i64>(1)?)),
+            )
+            .ok();
+
+        match settings {
+            Some((enabled, grace_period)) => NicknameLockSettings {
+                guild_id,
+                enabled,
+                grace_period: grace_period as u64,
+            },
+            None => NicknameLockSettings {
+                guild_id,
+                enabled: false,
+                grace_period: 0,
+            },
+        }
+    }
+
+    pub fn set_nickname_lock_settings(&self, settings: &NicknameLockSettings) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO nickname_lock_settings (guild_id, enabled, grace_period) 
+             VALUES (?, ?, ?)",
+            params![
+                settings.guild_id.get() as i64,
+                settings.enabled,
+                settings.grace_period as i64
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn get_nickname_lock_exempt_roles(&self, guild_id: Id<GuildMarker>) -> Vec<Id<RoleMarker>> {
+        let conn = self.conn.lock().unwrap();
+
+        let Ok(mut stmt) =
+            conn.prepare("SELECT role_id FROM nickname_lock_exempt_roles WHERE guild_id = ?")
+        else {
+            return vec![];
+        };
+
+        let Ok(rows) = stmt.query_map(params![guild_id.get() as i64], |row| row.get::<_, i64>(0))
+        else {
+            return vec![];
+        };
+
+        rows.filter_map(|role_id| role_id.ok())
+            .filter_map(|role_id| Id::new_checked(role_id as u64))
+            .collect()
+    }
+
+    pub fn add_nickname_lock_exempt_role(
+        &self,
+        guild_id: Id<GuildMarker>,
+        role_id: Id<RoleMarker>,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR IGNORE INTO nickname_lock_exempt_roles (guild_id, role_id) VALUES (?, ?)",
+            params![guild_id.get() as i64, role_id.get() as i64],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn remove_nickname_lock_exempt_role(
+        &self,
+        guild_id: Id<GuildMarker>,
+        role_id: Id<RoleMarker>,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "DELETE FROM nickname_lock_exempt_roles WHERE guild_id = ? AND role_id = ?",
+            params![guild_id.get() as i64, role_id.get() as i64],
+        )?;
+
+        Ok(())
+    }
+
+    /// Count a nickname reversion against a member and return their total
+    pub fn record_nickname_offence(
+        &self,
+        guild_id: Id<GuildMarker>,
+        discord_user: Id<UserMarker>,
+    ) -> SqliteResult<u32> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT INTO nickname_offences (guild_id, discord_user, count) VALUES (?, ?, 1) 
+             ON CONFLICT(guild_id, discord_user) DO UPDATE SET count = count + 1",
+            params![guild_id.get() as i64, discord_user.get() as i64],
+        )?;
+
+        conn.query_row(
+            "SELECT count FROM nickname_offences WHERE guild_id = ? AND discord_user = ?",
+            params![guild_id.get() as i64, discord_user.get() as i64],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn get_role_policy(&self, guild_id: Id<GuildMarker>) -> RolePolicy {
+        let conn = self.conn.lock().unwrap();
+
+        conn.query_row(
+            "SELECT policy FROM role_policies WHERE guild_id = ?",
+            params![guild_id.get() as i64],
+            |row| row.get::<_, String>(0),
+        )
+        .ok()
+        .and_then(|policy| RolePolicy::from_name(&policy))
+        .unwrap_or(RolePolicy::RecordOverride)
+    }
+
+    pub fn set_role_policy(
+        &self,
+        guild_id: Id<GuildMarker>,
+        policy: RolePolicy,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO role_policies (guild_id, policy) VALUES (?, ?)",
+            params![guild_id.get() as i64, policy.as_str()],
+        )?;
+
+        Ok(())
+    }
+
+    /// `Some(has_role)` when staff manually gave or took the verified role against the link
+    pub fn get_role_override(
+        &self,
+        guild_id: Id<GuildMarker>,
+        discord_user: Id<UserMarker>,
+    ) -> Option<bool> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.query_row(
+            "SELECT has_role FROM role_overrides WHERE guild_id = ? AND discord_user = ?",
+            params![guild_id.get() as i64, discord_user.get() as i64],
+            |row| row.get(0),
+        )
+        .ok()
+    }
+
+    pub fn set_role_override(
+        &self,
+        guild_id: Id<GuildMarker>,
+        discord_user: Id<UserMarker>,
+        has_role: bool,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO role_overrides (guild_id, discord_user, has_role) VALUES (?, ?, ?)",
+            params![guild_id.get() as i64, discord_user.get() as i64, has_role],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn remove_role_override(
+        &self,
+        guild_id: Id<GuildMarker>,
+        discord_user: Id<UserMarker>,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "DELETE FROM role_overrides WHERE guild_id = ? AND discord_user = ?",
+            params![guild_id.get() as i64, discord_user.get() as i64],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn add_link_audit(&self, entry: &LinkAuditEntry) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT INTO link_audit (guild_id, discord_user, action, detail, created_at) 
+             VALUES (?, ?, ?, ?, ?)",
+            params![
+                entry.guild_id.map(|guild_id| guild_id.get() as i64),
+                entry.discord_user.get() as i64,
+                entry.action,
+                entry.detail,
+                entry.created_at as i64
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Number of audit trail entries in a scope
+    pub fn count_link_audit(&self, scope: AuditScope) -> usize {
+        let conn = self.conn.lock().unwrap();
+        let (condition, values) = scope.condition();
+
+        conn.query_row(
+            &format!("SELECT COUNT(*) FROM link_audit WHERE {}", condition),
+            params_from_iter(values),
+            |row| row.get::<_, i64>(0),
+        )
+        .map(|count| count as usize)
+        .unwrap_or(0)
+    }
+
+    /// One page of the audit trail in a scope, newest first
+    pub fn get_link_audit_page(
+        &self,
+        scope: AuditScope,
+        offset: usize,
+        limit: usize,
+    ) -> Vec<LinkAuditEntry> {
+        let conn = self.conn.lock().unwrap();
+        let (condition, mut values) = scope.condition();
+        values.extend([limit as i64, offset as i64]);
+
+        let Ok(mut stmt) = conn.prepare(&format!(
+            "SELECT guild_id, discord_user, action, detail, created_at FROM link_audit 
+             WHERE {} ORDER BY id DESC LIMIT ? OFFSET ?",
+            condition
+        )) else {
+            return vec![];
+        };
+
+        let Ok(rows) = stmt.query_map(params_from_iter(values), |row| {
+            Ok(LinkAuditEntry {
+                guild_id: row
+                    .get::<_, Option<i64>>(0)?
+                    .and_then(|guild_id| Id::new_checked(guild_id as u64)),
+                discord_user: Id::new(row.get::<_, i64>(1)? as u64),
+                action: row.get(2)?,
+                detail: row.get(3)?,
+                created_at: row.get::<_, i64>(4)? as u64,
+            })
+        }) else {
+            return vec![];
+        };
+
+        rows.filter_map(|entry| entry.ok()).collect()
+    }
+
+    /// Log categories are enabled unless a guild turned them off
+    pub fn is_log_category_enabled(&self, guild_id: Id<GuildMarker>, category: &str) -> bool {
+        let conn = self.conn.lock().unwrap();
+
+        conn.query_row(
+            "SELECT enabled FROM log_categories WHERE guild_id = ? AND category = ?",
+            params![guild_id.get() as i64, category],
+            |row| row.get(0),
+        )
+        .unwrap_or(true)
+    }
+
+    pub fn set_log_category_enabled(
+        &self,
+        guild_id: Id<GuildMarker>,
+        category: &str,
+        enabled: bool,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO log_categories (guild_id, category, enabled) VALUES (?, ?, ?)",
+            params![guild_id.get() as i64, category, enabled],
+        )?;
+
+        Ok(())
+    }
+
+    /// Add a job to the outbox, it will be picked up by the job worker
+    pub fn enqueue_job(&self, kind: &JobKind) -> SqliteResult<i64> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT INTO jobs (kind, guild_id, discord_user, target, payload, components, 
+             next_attempt_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![
+                kind.as_str(),
+                kind.guild_id().map(|guild_id| guild_id.get() as i64),
+                kind.discord_user().get() as i64,
+                kind.target(),
+                kind.payload(),
+                kind.components(),
+                unix_now() as i64
+            ],
+        )?;
+
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Pending jobs that are due, oldest first
+    ///
+    /// Jobs that can't be read back, e.g. of a kind this version doesn't know, are marked as
+    /// failed so they aren't picked up again on every poll.
+    pub fn get_due_jobs(&self, limit: u32) -> Vec<Job> {
+        let conn = self.conn.lock().unwrap();
+
+        let Ok(mut stmt) = conn.prepare(
+            "SELECT id, kind, guild_id, discord_user, target, payload, attempts, last_error, 
+             components FROM jobs WHERE status = 'pending' AND next_attempt_at <= ? 
+             ORDER BY id LIMIT ?",
+        ) else {
+            return vec![];
+        };
+
+        let Ok(rows) = stmt.query_map(params![unix_now() as i64, limit], |row| {
+            Ok((row.get::<_, i64>(0)?, job_from_row(row).ok().flatten()))
+        }) else {
+            return vec![];
+        };
+
+        let mut jobs = Vec::new();
+        let mut unreadable = Vec::new();
+        for (id, job) in rows.filter_map(|row| row.ok()) {
+            match job {
+                Some(job) => jobs.push(job),
+                None => unreadable.push(id),
+            }
+        }
+        drop(stmt);
+
+        for id in unreadable {
+            warn!("Job {} could not be read, marking it as failed", id);
+
+            if let Err(error) = conn.execute(
+                "UPDATE jobs SET status = 'failed', last_error = 'could not be read' WHERE id = ?",
+                params![id],
+            ) {
+                error!("Could not mark job {} as failed: {}", id, error);
+            }
+        }
+
+        jobs
+    }
+
+    pub fn complete_job(&self, id: i64) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute("DELETE FROM jobs WHERE id = ?", params![id])?;
+
+        Ok(())
+    }
+
+    /// Try the job again after `delay_secs`
+    pub fn retry_job(&self, id: i64, delay_secs: u64, error: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "UPDATE jobs SET attempts = attempts + 1, next_attempt_at = ?, last_error = ? 
+             WHERE id = ?",
+            params![(unix_now() + delay_secs) as i64, error, id],
+        )?;
+
+        Ok(())
+    }
+
+    /// Give up on the job, it stays in the table for `/diagnose`
+    pub fn fail_job(&self, id: i64, error: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "UPDATE jobs SET status = 'failed', attempts = attempts + 1, last_error = ? 
+             WHERE id = ?",
+            params![error, id],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn get_job_queue_depth(&self, guild_id: Option<Id<GuildMarker>>) -> JobQueueDepth {
+        let conn = self.conn.lock().unwrap();
+
+        let mut depth = JobQueueDepth::default();
+
+        let Ok(mut stmt) = conn.prepare(
+            "SELECT status, COUNT(*) FROM jobs WHERE ?1 IS NULL OR guild_id = ?1 GROUP BY status",
+        ) else {
+            return depth;
+        };
+
+        let Ok(rows) = stmt.query_map(
+            params![guild_id.map(|guild_id| guild_id.get() as i64)],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, u64>(1)?)),
+        ) else {
+            return depth;
+        };
+
+        for (status, count) in rows.filter_map(|row| row.ok()) {
+            match status.as_str() {
+                "pending" => depth.pending = count,
+                "failed" => depth.failed = count,
+                _ => {}
+            }
+        }
+
+        depth
+    }
+
+    /// Whether a role change of the member is still waiting in the outbox
+    pub fn has_pending_role_job(
+        &self,
+        guild_id: Id<GuildMarker>,
+        discord_user: Id<UserMarker>,
+    ) -> bool {
+        let conn = self.conn.lock().unwrap();
+
+        conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM jobs WHERE status = 'pending' 
+             AND kind IN ('add_role', 'remove_role') AND guild_id = ? AND discord_user = ?)",
+            params![guild_id.get() as i64, discord_user.get() as i64],
+            |row| row.get(0),
+        )
+        .unwrap_or(false)
+    }
+
+    /// Most recent permanently failed jobs of a guild
+    pub fn get_failed_jobs(&self, guild_id: Id<GuildMarker>, limit: u32) -> Vec<Job> {
+        let conn = self.conn.lock().unwrap();
+
+        let Ok(mut stmt) = conn.prepare(
+            "SELECT id, kind, guild_id, discord_user, target, payload, attempts, last_error, 
+             components FROM jobs WHERE status = 'failed' AND guild_id = ? ORDER BY id DESC 
+             LIMIT ?",
+        ) else {
+            return vec![];
+        };
+
+        let Ok(rows) = stmt.query_map(params![guild_id.get() as i64, limit], job_from_row) else {
+            return vec![];
+        };
+
+        rows.filter_map(|job| job.ok().flatten()).collect()
+    }
+
+    pub fn get_dm_fallback_settings(&self, guild_id: Id<GuildMarker>) -> DmFallbackSettings {
+        let conn = self.conn.lock().unwrap();
+
+        let settings = conn
+            .query_row(
+                "SELECT policy, delete_after FROM dm_fallback_settings WHERE guild_id = ?",
+                params![guild_id.get() as i64],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+            )
+            .ok();
+
+        match settings {
+            Some((policy, delete_after)) => DmFallbackSettings {
+                guild_id,
+                policy: DmFallbackPolicy::from_name(&policy).unwrap_or(DmFallbackPolicy::Mention),
+                delete_after: delete_after as u64,
+            },
+            None => DmFallbackSettings {
+                guild_id,
+                policy: DmFallbackPolicy::Mention,
+                delete_after: 10,
+            },
+        }
+    }
+
+    pub fn set_dm_fallback_settings(&self, settings: &DmFallbackSettings) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO dm_fallback_settings (guild_id, policy, delete_after) 
+             VALUES (?, ?, ?)",
+            params![
+                settings.guild_id.get() as i64,
+                settings.policy.as_str(),
+                settings.delete_after as i64
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// How the user was last reached and when (unix seconds)
+    pub fn get_dm_delivery(&self, discord_user: Id<UserMarker>) -> Option<(DeliveryChannel, u64)> {
+        let conn = self.conn.lock().unwrap();
+
+        let (channel, updated_at) = conn
+            .query_row(
+                "SELECT channel, updated_at FROM dm_delivery WHERE discord_user = ?",
+                params![discord_user.get() as i64],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+            )
+            .ok()?;
+
+        Some((DeliveryChannel::from_name(&channel)?, updated_at as u64))
+    }
+
+    pub fn set_dm_delivery(
+        &self,
+        discord_user: Id<UserMarker>,
+        channel: DeliveryChannel,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO dm_delivery (discord_user, channel, updated_at) VALUES (?, ?, ?)",
+            params![discord_user.get() as i64, channel.as_str(), unix_now() as i64],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn add_to_dm_digest(
+        &self,
+        guild_id: Id<GuildMarker>,
+        discord_user: Id<UserMarker>,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR IGNORE INTO dm_digest (guild_id, discord_user) VALUES (?, ?)",
+            params![guild_id.get() as i64, discord_user.get() as i64],
+        )?;
+
+        Ok(())
+    }
+
+    /// Everyone waiting in the guild's digest
+    pub fn get_dm_digest(&self, guild_id: Id<GuildMarker>) -> Vec<Id<UserMarker>> {
+        let conn = self.conn.lock().unwrap();
+
+        let Ok(mut stmt) = conn.prepare("SELECT discord_user FROM dm_digest WHERE guild_id = ?")
+        else {
+            return vec![];
+        };
+
+        let Ok(rows) = stmt.query_map(params![guild_id.get() as i64], |row| row.get::<_, i64>(0))
+        else {
+            return vec![];
+        };
+
+        rows.filter_map(|user| user.ok())
+            .filter_map(|user| Id::new_checked(user as u64))
+            .collect()
+    }
+
+    /// Take users out of the digest once it was posted, users added since then stay in it
+    pub fn remove_from_dm_digest(
+        &self,
+        guild_id: Id<GuildMarker>,
+        discord_users: &[Id<UserMarker>],
+    ) -> SqliteResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let transaction = conn.transaction()?;
+
+        for discord_user in discord_users {
+            transaction.execute(
+                "DELETE FROM dm_digest WHERE guild_id = ? AND discord_user = ?",
+                params![guild_id.get() as i64, discord_user.get() as i64],
+            )?;
+        }
+
+        transaction.commit()
+    }
+
+    /// When the guild's digest was last posted (unix seconds)
+    pub fn get_last_dm_digest(&self, guild_id: Id<GuildMarker>) -> Option<u64> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.query_row(
+            "SELECT posted_at FROM dm_digest_runs WHERE guild_id = ?",
+            params![guild_id.get() as i64],
+            |row| row.get::<_, i64>(0),
+        )
+        .ok()
+        .map(|posted_at| posted_at as u64)
+    }
+
+    pub fn set_last_dm_digest(
+        &self,
+        guild_id: Id<GuildMarker>,
+        posted_at: u64,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO dm_digest_runs (guild_id, posted_at) VALUES (?, ?)",
+            params![guild_id.get() as i64, posted_at as i64],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn get_dm_digest_guilds(&self) -> Vec<Id<GuildMarker>> {
+        let conn = self.conn.lock().unwrap();
+
+        let Ok(mut stmt) = conn.prepare("SELECT DISTINCT guild_id FROM dm_digest") else {
+            return vec![];
+        };
+
+        let Ok(rows) = stmt.query_map([], |row| row.get::<_, i64>(0)) else {
+            return vec![];
+        };
+
+        rows.filter_map(|guild_id| guild_id.ok())
+            .filter_map(|guild_id| Id::new_checked(guild_id as u64))
+            .collect()
+    }
+
+    /// Guilds global commands were deployed to while developing
+    pub fn get_dev_guilds(&self) -> Vec<Id<GuildMarker>> {
+        let conn = self.conn.lock().unwrap();
+
+        let Ok(mut stmt) = conn.prepare("SELECT guild_id FROM dev_guilds") else {
+            return vec![];
+        };
+
+        let Ok(rows) = stmt.query_map([], |row| row.get::<_, i64>(0)) else {
+            return vec![];
+        };
+
+        rows.filter_map(|guild_id| guild_id.ok())
+            .filter_map(|guild_id| Id::new_checked(guild_id as u64))
+            .collect()
+    }
+
+    pub fn add_dev_guilds(&self, guild_ids: &[Id<GuildMarker>]) -> SqliteResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let transaction = conn.transaction()?;
+
+        for guild_id in guild_ids {
+            transaction.execute(
+                "INSERT OR IGNORE INTO dev_guilds (guild_id) VALUES (?)",
+                params![guild_id.get() as i64],
+            )?;
+        }
+
+        transaction.commit()
+    }
+
+    /// Delete a message once `delete_at` (unix seconds) has passed, see `get_due_deletions`
+    pub fn schedule_deletion(
+        &self,
+        channel_id: Id<ChannelMarker>,
+        message_id: Id<MessageMarker>,
+        delete_at: u64,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO scheduled_deletions (message_id, channel_id, delete_at) 
+             VALUES (?, ?, ?)",
+            params![
+                message_id.get() as i64,
+                channel_id.get() as i64,
+                delete_at as i64
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Messages that are due to be deleted
+    pub fn get_due_deletions(&self) -> Vec<(Id<ChannelMarker>, Id<MessageMarker>)> {
+        let conn = self.conn.lock().unwrap();
+
+        let Ok(mut stmt) = conn
+            .prepare("SELECT channel_id, message_id FROM scheduled_deletions WHERE delete_at <= ?")
+        else {
+            return vec![];
+        };
+
+        let Ok(rows) = stmt.query_map(params![unix_now() as i64], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+        }) else {
+            return vec![];
+        };
+
+        rows.filter_map(|row| row.ok())
+            .filter_map(|(channel_id, message_id)| {
+                Some((
+                    Id::new_checked(channel_id as u64)?,
+                    Id::new_checked(message_id as u64)?,
+                ))
+            })
+            .collect()
+    }
+
+    pub fn remove_deletion(&self, message_id: Id<MessageMarker>) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "DELETE FROM scheduled_deletions WHERE message_id = ?",
+            params![message_id.get() as i64],
+        )?;
+
+        Ok(())
+    }
+
+    /// A guild's override of a message, `locale` is a Discord locale code or `default`
+    pub fn get_guild_message(
+        &self,
+        guild_id: Id<GuildMarker>,
+        message_id: &str,
+        locale: &str,
+    ) -> Option<String> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.query_row(
+            "SELECT content FROM guild_messages WHERE guild_id = ? AND message_id = ? AND locale = ?",
+            params![guild_id.get() as i64, message_id, locale],
+            |row| row.get(0),
+        )
+        .ok()
+    }
+
+    pub fn set_guild_message(
+        &self,
+        guild_id: Id<GuildMarker>,
+        message_id: &str,
+        locale: &str,
+        content: &str,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO guild_messages (guild_id, message_id, locale, content) 
+             VALUES (?, ?, ?, ?)",
+            params![guild_id.get() as i64, message_id, locale, content],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn remove_guild_message(
+        &self,
+        guild_id: Id<GuildMarker>,
+        message_id: &str,
+        locale: &str,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "DELETE FROM guild_messages WHERE guild_id = ? AND message_id = ? AND locale = ?",
+            params![guild_id.get() as i64, message_id, locale],
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Expects `id, kind, guild_id, discord_user, target, payload, attempts, last_error, components`,
+/// rows with an unknown kind are skipped
+fn job_from_row(row: &Row) -> SqliteResult<Option<Job>> {
+    let kind = JobKind::from_columns(
+        &row.get::<_, String>(1)?,
+        row.get(2)?,
+        row.get(3)?,
+        row.get(4)?,
+        row.get(5)?,
+        row.get(8)?,
+    );
+
+    let Some(kind) = kind else {
+        return Ok(None);
+    };
+
+    Ok(Some(Job {
+        id: row.get(0)?,
+        kind,
+        attempts: row.get(6)?,
+        last_error: row.get(7)?,
+    }))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
 }
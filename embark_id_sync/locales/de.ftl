@@ -0,0 +1,30 @@
+join_not_linked =
+    `{guild}` nutzt diesen Bot zum Verknüpfen von Embark IDs.
+    Bitte gehe zu {channel} und folge den Anweisungen, um dein Konto zu verknüpfen.
+join_already_linked =
+    `{guild}` nutzt diesen Bot zum Verknüpfen von Embark IDs.
+    Da du dein Konto bereits verknüpft hast (`{embark_id}`), musst du nichts weiter tun. GLHF Contestant!
+apply_link_prompt =
+    `{guild}` nutzt diesen Bot zum Verknüpfen von Embark IDs.
+    Deine bestehende Verknüpfung (`{embark_id}`) hier übernehmen?
+apply_link_button = Meine Verknüpfung hier übernehmen
+apply_link_queued = `{embark_id}` wird in Kürze auf diesem Server übernommen
+apply_link_failed = Deine Verknüpfung konnte nicht übernommen werden, bitte versuche es später erneut
+apply_link_unavailable = Diese Verknüpfung kann nicht mehr übernommen werden
+invalid_embark_id = Ungültige EmbarkID
+already_claimed = Diese EmbarkID wurde bereits von jemandem beansprucht
+embark_id_entered = Deine Eingabe: {embark_id}
+verify_modal_title = Gib deine EmbarkID an
+verify_modal_label = EMBARKID
+verification_message = Verifizierung
+verify_button = verifizieren
+setup_prompt = Bitte führe /setup aus, um loszulegen.
+digest_header = Diese Mitglieder müssen ihre Embark ID noch verknüpfen, bitte folgt den Anweisungen oben:
+fallback_mention = wir konnten dir keine DM schicken, bitte sieh dir die Anweisungen zur Verifizierung in diesem Kanal an.
+guild_only = Dieser Befehl muss in einem Server ausgeführt werden!
+setup_complete = Einrichtung abgeschlossen!
+setup_channel_failed = Der Kanal konnte nicht erstellt werden
+setup_message_failed = Die Nachricht konnte nicht gesendet werden!
+setup_role_failed = Die Rolle konnte nicht erstellt werden
+
+command-setup-description = Richtet den Bot automatisch ein
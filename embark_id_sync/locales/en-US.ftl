@@ -0,0 +1,34 @@
+# Messages members see, guilds can override them with /messages
+# Placeholders are written as {name}
+
+join_not_linked =
+    `{guild}` uses this bot for Embark ID linking.
+    Please go to {channel} and follow the instructions to link your account.
+join_already_linked =
+    `{guild}` uses this bot for Embark ID linking.
+    Since your have already linked your account (`{embark_id}`) there is nothing that you need to do. GLHF Contestant!
+apply_link_prompt =
+    `{guild}` uses this bot for Embark ID linking.
+    Apply your existing link (`{embark_id}`) here?
+apply_link_button = Apply my existing link here
+apply_link_queued = `{embark_id}` will be applied in that server shortly
+apply_link_failed = Could not apply your link, please try again later
+apply_link_unavailable = This link can no longer be applied
+invalid_embark_id = Invalid EmbarkID
+already_claimed = Someone has already claimed this EmbarkID
+embark_id_entered = You entered: {embark_id}
+verify_modal_title = Provide Your EmbarkID
+verify_modal_label = EMBARKID
+verification_message = verification message
+verify_button = verify
+setup_prompt = Please run /setup to get started.
+digest_header = These members still need to link their Embark ID, please follow the instructions above:
+fallback_mention = we could not DM you, please check the verification instructions in this channel.
+guild_only = This command must be done in a guild!
+setup_complete = Setup complete!
+setup_channel_failed = Could not create channel
+setup_message_failed = Could not send message!
+setup_role_failed = Could not create role
+
+# Command definitions
+command-setup-description = Automatically sets up the bot
@@ -0,0 +1,30 @@
+join_not_linked =
+    `{guild}` usa este bot para vincular Embark IDs.
+    Ve a {channel} y sigue las instrucciones para vincular tu cuenta.
+join_already_linked =
+    `{guild}` usa este bot para vincular Embark IDs.
+    Como ya has vinculado tu cuenta (`{embark_id}`), no tienes que hacer nada. GLHF Contestant!
+apply_link_prompt =
+    `{guild}` usa este bot para vincular Embark IDs.
+    ¿Aplicar aquí tu vínculo existente (`{embark_id}`)?
+apply_link_button = Aplicar mi vínculo aquí
+apply_link_queued = `{embark_id}` se aplicará en ese servidor en breve
+apply_link_failed = No se pudo aplicar tu vínculo, inténtalo de nuevo más tarde
+apply_link_unavailable = Este vínculo ya no se puede aplicar
+invalid_embark_id = EmbarkID no válido
+already_claimed = Alguien ya ha reclamado este EmbarkID
+embark_id_entered = Has introducido: {embark_id}
+verify_modal_title = Introduce tu EmbarkID
+verify_modal_label = EMBARKID
+verification_message = mensaje de verificación
+verify_button = verificar
+setup_prompt = Ejecuta /setup para empezar.
+digest_header = Estos miembros aún tienen que vincular su Embark ID, seguid las instrucciones de arriba:
+fallback_mention = no hemos podido enviarte un MD, revisa las instrucciones de verificación de este canal.
+guild_only = ¡Este comando debe usarse en un servidor!
+setup_complete = ¡Configuración completada!
+setup_channel_failed = No se ha podido crear el canal
+setup_message_failed = ¡No se ha podido enviar el mensaje!
+setup_role_failed = No se ha podido crear el rol
+
+command-setup-description = Configura el bot automáticamente
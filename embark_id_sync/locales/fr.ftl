@@ -0,0 +1,30 @@
+join_not_linked =
+    `{guild}` utilise ce bot pour lier les Embark ID.
+    Rends-toi dans {channel} et suis les instructions pour lier ton compte.
+join_already_linked =
+    `{guild}` utilise ce bot pour lier les Embark ID.
+    Comme tu as déjà lié ton compte (`{embark_id}`), tu n'as rien à faire. GLHF Contestant!
+apply_link_prompt =
+    `{guild}` utilise ce bot pour lier les Embark ID.
+    Appliquer ton lien existant (`{embark_id}`) ici ?
+apply_link_button = Appliquer mon lien ici
+apply_link_queued = `{embark_id}` sera appliqué sur ce serveur sous peu
+apply_link_failed = Impossible d'appliquer ton lien, réessaie plus tard
+apply_link_unavailable = Ce lien ne peut plus être appliqué
+invalid_embark_id = EmbarkID invalide
+already_claimed = Quelqu'un a déjà revendiqué cet EmbarkID
+embark_id_entered = Tu as saisi : {embark_id}
+verify_modal_title = Indique ton EmbarkID
+verify_modal_label = EMBARKID
+verification_message = message de vérification
+verify_button = vérifier
+setup_prompt = Lance /setup pour commencer.
+digest_header = Ces membres doivent encore lier leur Embark ID, suivez les instructions ci-dessus :
+fallback_mention = nous n'avons pas pu t'envoyer de MP, consulte les instructions de vérification de ce salon.
+guild_only = Cette commande doit être utilisée dans un serveur !
+setup_complete = Configuration terminée !
+setup_channel_failed = Impossible de créer le salon
+setup_message_failed = Impossible d'envoyer le message !
+setup_role_failed = Impossible de créer le rôle
+
+command-setup-description = Configure le bot automatiquement
@@ -0,0 +1,289 @@
+use async_trait::async_trait;
+use common::commands::collectors::ConfirmDialog;
+use common::commands::options::FromCommandData;
+use common::commands::pagination::Paginator;
+use common::commands::subcommands::{Subcommand, SubcommandTree};
+use common::commands::{CommandContext, CommandError};
+use data::{AuditScope, EmbarkID, GuildSettings, User};
+use std::sync::Arc;
+use tracing::error;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{GuildMarker, UserMarker};
+
+use crate::audit_log::{AuditCategory, AuditEvent, AuditTrailPages};
+use crate::{EmbarkIDSync, manage_guild_access, managed_guild, unlink_user};
+
+/// `/admin`, manage the links of other members
+pub fn admin_command(sync: EmbarkIDSync) -> SubcommandTree {
+    SubcommandTree::new("admin", "Manage the Embark ID links of members")
+        .subcommand(LinkMember { sync: sync.clone() })
+        .subcommand(UnlinkMember { sync: sync.clone() })
+        .subcommand(GuildHistory { sync })
+        .access(manage_guild_access())
+}
+
+fn require_member(
+    context: &CommandContext,
+    guild_id: Id<GuildMarker>,
+    user_id: Id<UserMarker>,
+) -> Result<(), CommandError> {
+    if context.context.cache.member(guild_id, user_id).is_none() {
+        return Err(CommandError::Validation(format!(
+            "<@{}> is not a member of this server",
+            user_id
+        )));
+    }
+
+    Ok(())
+}
+
+#[derive(FromCommandData)]
+struct LinkOptions {
+    /// The member to link
+    user: Id<UserMarker>,
+    /// Their Embark ID, e.g. name#1234
+    embark_id: String,
+}
+
+struct LinkMember {
+    sync: EmbarkIDSync,
+}
+
+#[async_trait]
+impl Subcommand for LinkMember {
+    type Options = LinkOptions;
+
+    fn name(&self) -> &str {
+        "link"
+    }
+
+    fn description(&self) -> &str {
+        "Link an Embark ID to a member"
+    }
+
+    async fn execute(
+        &self,
+        context: &mut CommandContext,
+        options: LinkOptions,
+    ) -> Result<(), CommandError> {
+        let guild_id = managed_guild(context)?;
+        let Some(admin) = context.get_user_id() else {
+            return Err(CommandError::Internal("Interaction without a user".into()));
+        };
+        let database = &self.sync.database;
+
+        // Links are shared by every guild, only members of this one can be changed from here
+        require_member(context, guild_id, options.user)?;
+
+        let Ok(embark_id) = EmbarkID::new(&options.embark_id) else {
+            return Err(CommandError::Validation(format!(
+                "`{}` is not a valid Embark ID",
+                options.embark_id
+            )));
+        };
+
+        if let Some(owner) = database
+            .get_user_by_embark_id(&embark_id)
+            .filter(|owner| owner.discord_user != options.user)
+        {
+            return Err(CommandError::Validation(format!(
+                "`{}` is already linked to <@{}>",
+                embark_id.to_string(),
+                owner.discord_user
+            )));
+        }
+
+        let user = User {
+            discord_user: options.user,
+            embark_id: embark_id.clone(),
+        };
+
+        let event = match database.get_user_by_discord_id(options.user) {
+            Some(previous) => {
+                database
+                    .update_user_embark_id(options.user, &embark_id)
+                    .map_err(|error| CommandError::failed("Could not update link!", error))?;
+
+                AuditEvent::new(
+                    AuditCategory::Relink,
+                    options.user,
+                    "had their Embark ID changed by an admin",
+                )
+                .field("Previous", previous.embark_id.to_string())
+                .field("New", embark_id.to_string())
+            }
+            None => {
+                database
+                    .add_user(&user)
+                    .map_err(|error| CommandError::failed("Could not save link!", error))?;
+
+                AuditEvent::new(AuditCategory::Link, options.user, "was linked by an admin")
+                    .field("Embark ID", embark_id.to_string())
+            }
+        };
+
+        self.sync.audit(
+            &context.context,
+            Some(guild_id),
+            event.field("Admin", format!("<@{}>", admin)),
+        );
+
+        if let Some(guild_settings) = database.get_guild_settings(&guild_id) {
+            self.sync
+                .sync_member(&context.context, &user, &guild_settings);
+        }
+
+        context
+            .reply_ephemeral(format!(
+                "<@{}> is now linked to `{}`",
+                options.user,
+                embark_id.to_string()
+            ))
+            .await
+    }
+}
+
+#[derive(FromCommandData)]
+struct UnlinkOptions {
+    /// The member to unlink
+    user: Id<UserMarker>,
+}
+
+struct UnlinkMember {
+    sync: EmbarkIDSync,
+}
+
+#[async_trait]
+impl Subcommand for UnlinkMember {
+    type Options = UnlinkOptions;
+
+    fn name(&self) -> &str {
+        "unlink"
+    }
+
+    fn description(&self) -> &str {
+        "Remove the Embark ID link of a member"
+    }
+
+    async fn execute(
+        &self,
+        context: &mut CommandContext,
+        options: UnlinkOptions,
+    ) -> Result<(), CommandError> {
+        let guild_id = managed_guild(context)?;
+        let Some(admin) = context.get_user_id() else {
+            return Err(CommandError::Internal("Interaction without a user".into()));
+        };
+        let database = &self.sync.database;
+
+        require_member(context, guild_id, options.user)?;
+
+        let Some(user) = database.get_user_by_discord_id(options.user) else {
+            return Err(CommandError::Validation(format!(
+                "<@{}> has not linked an Embark ID",
+                options.user
+            )));
+        };
+
+        let confirmed = ConfirmDialog::new(format!(
+            "Really unlink <@{}> from `{}`?",
+            options.user,
+            user.embark_id.to_string()
+        ))
+        .confirm_label("Unlink")
+        .ask(context)
+        .await?;
+        if !confirmed {
+            return Ok(());
+        }
+
+        // The link is gone everywhere, so take it back in every guild it was applied in, and
+        // only take back nicknames the bot was the one to set
+        let applied_in: Vec<(GuildSettings, bool)> = context
+            .context
+            .cache
+            .user_guilds(options.user)
+            .map(|guild_ids| {
+                guild_ids
+                    .value()
+                    .iter()
+                    .filter(|guild_id| !database.needs_consent(options.user, **guild_id))
+                    .filter_map(|guild_id| database.get_guild_settings(guild_id))
+                    .map(|guild_settings| {
+                        let had_nickname =
+                            database.should_set_nickname(options.user, guild_settings.guild_id);
+                        (guild_settings, had_nickname)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        database
+            .remove_user(options.user)
+            .map_err(|error| CommandError::failed("Could not remove link!", error))?;
+
+        for (guild_settings, had_nickname) in applied_in {
+            if let Err(error) = unlink_user(database, options.user, &guild_settings, had_nickname) {
+                error!(
+                    "Could not queue unlink of {} in {}: {}",
+                    options.user, guild_settings.guild_id, error
+                );
+            }
+        }
+
+        self.sync.audit(
+            &context.context,
+            Some(guild_id),
+            AuditEvent::new(
+                AuditCategory::Unlink,
+                options.user,
+                "was unlinked by an admin",
+            )
+            .field("Embark ID", user.embark_id.to_string())
+            .field("Admin", format!("<@{}>", admin)),
+        );
+
+        context
+            .reply_ephemeral(format!(
+                "<@{}> is no longer linked to `{}`",
+                options.user,
+                user.embark_id.to_string()
+            ))
+            .await
+    }
+}
+
+struct GuildHistory {
+    sync: EmbarkIDSync,
+}
+
+#[async_trait]
+impl Subcommand for GuildHistory {
+    type Options = ();
+
+    fn name(&self) -> &str {
+        "history"
+    }
+
+    fn description(&self) -> &str {
+        "List the link changes in this server, newest first"
+    }
+
+    async fn execute(
+        &self,
+        context: &mut CommandContext,
+        _options: (),
+    ) -> Result<(), CommandError> {
+        let guild_id = managed_guild(context)?;
+
+        let pages = AuditTrailPages::new(
+            Arc::clone(&self.sync.database),
+            AuditScope::Guild(guild_id),
+            "Link history of this server",
+        );
+        Paginator::new(pages)
+            .empty("No links have changed in this server yet")
+            .send(context, true)
+            .await
+    }
+}
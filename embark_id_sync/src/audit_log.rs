@@ -0,0 +1,481 @@
+use async_trait::async_trait;
+use common::commands::access::CommandAccess;
+use common::commands::pagination::PageSource;
+use common::commands::{CommandBundle, CommandContext, CommandError};
+use data::{AuditScope, Database, LinkAuditEntry};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, error};
+use twilight_model::application::command::{Command, CommandType};
+use twilight_model::application::interaction::application_command::CommandData;
+use twilight_model::channel::ChannelType;
+use twilight_model::channel::message::Embed;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, GuildMarker, UserMarker};
+use twilight_model::util::Timestamp;
+use twilight_util::builder::command::{
+    BooleanBuilder, ChannelBuilder, CommandBuilder, StringBuilder,
+};
+use twilight_util::builder::embed::{EmbedBuilder, EmbedFieldBuilder};
+
+use crate::context::Context;
+use crate::{EmbarkIDSync, manage_guild_access, managed_guild};
+
+/// How long events are collected before they are posted together
+const BATCH_WINDOW: Duration = Duration::from_secs(3);
+/// Discord allows up to 10 embeds per message
+const EMBEDS_PER_MESSAGE: usize = 10;
+/// Most characters of all embeds of a message combined
+const EMBED_LENGTH_PER_MESSAGE: usize = 6000;
+/// Longest embed field value Discord accepts
+const FIELD_VALUE_LENGTH: usize = 1024;
+/// Longest field value kept in the audit trail, so a page of entries stays readable
+const DETAIL_VALUE_LENGTH: usize = 200;
+/// Audit trail entries shown on one page of a listing
+const ENTRIES_PER_PAGE: usize = 10;
+
+/// Kinds of verification events that can be toggled per guild
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AuditCategory {
+    Link,
+    Relink,
+    Unlink,
+    FailedClaim,
+    DuplicateClaim,
+    UpdateFailure,
+    AdminOverride,
+    NicknameLock,
+    RoleChange,
+}
+
+impl AuditCategory {
+    pub const ALL: [AuditCategory; 9] = [
+        AuditCategory::Link,
+        AuditCategory::Relink,
+        AuditCategory::Unlink,
+        AuditCategory::FailedClaim,
+        AuditCategory::DuplicateClaim,
+        AuditCategory::UpdateFailure,
+        AuditCategory::AdminOverride,
+        AuditCategory::NicknameLock,
+        AuditCategory::RoleChange,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditCategory::Link => "link",
+            AuditCategory::Relink => "relink",
+            AuditCategory::Unlink => "unlink",
+            AuditCategory::FailedClaim => "failed_claim",
+            AuditCategory::DuplicateClaim => "duplicate_claim",
+            AuditCategory::UpdateFailure => "update_failure",
+            AuditCategory::AdminOverride => "admin_override",
+            AuditCategory::NicknameLock => "nickname_lock",
+            AuditCategory::RoleChange => "role_change",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        AuditCategory::ALL
+            .into_iter()
+            .find(|category| category.as_str() == name)
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            AuditCategory::Link => "Embark ID linked",
+            AuditCategory::Relink => "Embark ID changed",
+            AuditCategory::Unlink => "Embark ID unlinked",
+            AuditCategory::FailedClaim => "Failed claim",
+            AuditCategory::DuplicateClaim => "Duplicate claim",
+            AuditCategory::UpdateFailure => "Could not update member",
+            AuditCategory::AdminOverride => "Admin override",
+            AuditCategory::NicknameLock => "Nickname restored",
+            AuditCategory::RoleChange => "Verified role changed",
+        }
+    }
+
+    fn color(&self) -> u32 {
+        match self {
+            AuditCategory::Link | AuditCategory::Relink => 0x00c822,
+            AuditCategory::Unlink | AuditCategory::RoleChange => 0x3498db,
+            AuditCategory::FailedClaim | AuditCategory::NicknameLock => 0xf1c40f,
+            AuditCategory::DuplicateClaim | AuditCategory::UpdateFailure => 0xe74c3c,
+            AuditCategory::AdminOverride => 0x9b59b6,
+        }
+    }
+}
+
+pub struct AuditEvent {
+    pub category: AuditCategory,
+    pub user_id: Id<UserMarker>,
+    pub description: String,
+    pub fields: Vec<(String, String)>,
+}
+
+impl AuditEvent {
+    pub fn new(
+        category: AuditCategory,
+        user_id: Id<UserMarker>,
+        description: impl Into<String>,
+    ) -> Self {
+        AuditEvent {
+            category,
+            user_id,
+            description: description.into(),
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn field(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.fields.push((name.into(), value.into()));
+        self
+    }
+
+    fn to_embed(&self) -> Embed {
+        let mut embed = EmbedBuilder::new()
+            .title(self.category.title())
+            .description(format!("<@{}> {}", self.user_id, self.description))
+            .color(self.category.color());
+
+        for (name, value) in &self.fields {
+            embed = embed
+                .field(EmbedFieldBuilder::new(name, truncate(value, FIELD_VALUE_LENGTH)).inline());
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as i64)
+            .unwrap_or(0);
+        if let Ok(timestamp) = Timestamp::from_secs(now) {
+            embed = embed.timestamp(timestamp);
+        }
+
+        embed.build()
+    }
+
+    /// The description with its fields, as kept in the audit trail
+    fn detail(&self) -> String {
+        if self.fields.is_empty() {
+            return self.description.clone();
+        }
+
+        let fields: Vec<String> = self
+            .fields
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name, truncate(value, DETAIL_VALUE_LENGTH)))
+            .collect();
+        format!("{} ({})", self.description, fields.join(", "))
+    }
+}
+
+/// Cut `text` to at most `max` characters, ending in `…` when it was cut
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+
+    let mut truncated: String = text.chars().take(max - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Characters of an embed that count towards `EMBED_LENGTH_PER_MESSAGE`
+fn embed_length(embed: &Embed) -> usize {
+    let length = |text: &Option<String>| text.as_ref().map_or(0, |text| text.chars().count());
+
+    length(&embed.title)
+        + length(&embed.description)
+        + embed
+            .fields
+            .iter()
+            .map(|field| field.name.chars().count() + field.value.chars().count())
+            .sum::<usize>()
+}
+
+/// Group embeds into messages that stay within Discord's count and length limits
+fn batch_embeds(embeds: Vec<Embed>) -> Vec<Vec<Embed>> {
+    let mut batches: Vec<Vec<Embed>> = Vec::new();
+    let mut batch_length = 0;
+
+    for embed in embeds {
+        let length = embed_length(&embed);
+        let fits = batches.last().is_some_and(|batch| {
+            batch.len() < EMBEDS_PER_MESSAGE && batch_length + length <= EMBED_LENGTH_PER_MESSAGE
+        });
+        if !fits {
+            batches.push(Vec::new());
+            batch_length = 0;
+        }
+
+        batch_length += length;
+        if let Some(batch) = batches.last_mut() {
+            batch.push(embed);
+        }
+    }
+
+    batches
+}
+
+/// Collects log embeds per guild and posts them in batches
+#[derive(Default)]
+pub struct AuditLog {
+    pending: Mutex<HashMap<Id<GuildMarker>, Vec<Embed>>>,
+}
+
+impl AuditLog {
+    fn push(
+        self: &Arc<Self>,
+        context: Arc<Context>,
+        guild_id: Id<GuildMarker>,
+        channel_id: Id<ChannelMarker>,
+        embed: Embed,
+    ) {
+        let first_in_batch = {
+            let mut pending = self.pending.lock().unwrap();
+            let embeds = pending.entry(guild_id).or_default();
+            embeds.push(embed);
+            embeds.len() == 1
+        };
+
+        // The first event of a burst schedules the flush, the rest rides along
+        if first_in_batch {
+            let audit_log = Arc::clone(self);
+            tokio::spawn(async move {
+                tokio::time::sleep(BATCH_WINDOW).await;
+                audit_log.flush(&context, guild_id, channel_id).await;
+            });
+        }
+    }
+
+    async fn flush(
+        &self,
+        context: &Context,
+        guild_id: Id<GuildMarker>,
+        channel_id: Id<ChannelMarker>,
+    ) {
+        let embeds = self
+            .pending
+            .lock()
+            .unwrap()
+            .remove(&guild_id)
+            .unwrap_or_default();
+
+        for batch in batch_embeds(embeds) {
+            if let Err(error) = context
+                .client
+                .create_message(channel_id)
+                .embeds(&batch)
+                .await
+            {
+                error!("Could not post to log channel of {}: {}", guild_id, error);
+            }
+        }
+    }
+
+    /// Record an event in the audit trail and post it to the guild's log channel
+    pub fn record(
+        self: &Arc<Self>,
+        database: &Database,
+        context: &Arc<Context>,
+        guild_id: Option<Id<GuildMarker>>,
+        event: AuditEvent,
+    ) {
+        let entry = LinkAuditEntry::new(
+            guild_id,
+            event.user_id,
+            event.category.as_str(),
+            event.detail(),
+        );
+
+        if let Err(error) = database.add_link_audit(&entry) {
+            error!(
+                "Could not record {} for {}: {}",
+                entry.action, event.user_id, error
+            );
+        }
+
+        let Some(guild_id) = guild_id else {
+            return;
+        };
+
+        if !database.is_log_category_enabled(guild_id, event.category.as_str()) {
+            return;
+        }
+
+        let Some(channel_id) = database.get_log_channel(guild_id) else {
+            debug!("No log channel for {}: {}", guild_id, event.description);
+            return;
+        };
+
+        self.push(Arc::clone(context), guild_id, channel_id, event.to_embed());
+    }
+}
+
+impl EmbarkIDSync {
+    /// Record an entry in the link audit trail
+    pub fn record_audit(
+        &self,
+        guild_id: Option<Id<GuildMarker>>,
+        user_id: Id<UserMarker>,
+        action: &str,
+        detail: impl Into<String>,
+    ) {
+        let entry = LinkAuditEntry::new(guild_id, user_id, action, detail);
+
+        if let Err(error) = self.database.add_link_audit(&entry) {
+            error!("Could not record {} for {}: {}", action, user_id, error);
+        }
+    }
+
+    /// Record a verification event in the audit trail and post it to the guild's log channel
+    pub fn audit(
+        &self,
+        context: &Arc<Context>,
+        guild_id: Option<Id<GuildMarker>>,
+        event: AuditEvent,
+    ) {
+        self.audit_log
+            .record(&self.database, context, guild_id, event);
+    }
+}
+
+pub struct LogChannelCommand {
+    database: Arc<Database>,
+}
+
+impl LogChannelCommand {
+    pub fn new(database: Arc<Database>) -> Self {
+        LogChannelCommand { database }
+    }
+}
+
+#[async_trait]
+impl CommandBundle for LogChannelCommand {
+    fn definition(&self) -> Command {
+        CommandBuilder::new(
+            "logchannel",
+            "Set the channel verification events are posted in",
+            CommandType::ChatInput,
+        )
+        .option(
+            ChannelBuilder::new("channel", "The log channel")
+                .channel_types([ChannelType::GuildText])
+                .required(false),
+        )
+        .option(
+            StringBuilder::new("category", "An event category to turn on or off")
+                .choices(AuditCategory::ALL.map(|category| (category.title(), category.as_str())))
+                .required(false),
+        )
+        .option(BooleanBuilder::new("enabled", "Whether the category is posted").required(false))
+        .build()
+    }
+
+    fn access(&self) -> CommandAccess {
+        manage_guild_access()
+    }
+
+    async fn execute(
+        &self,
+        context: &mut CommandContext,
+        data: &CommandData,
+    ) -> Result<(), CommandError> {
+        let guild_id = managed_guild(context)?;
+
+        if let Some(channel_id) = context.get_channel_option("channel", data) {
+            self.database
+                .set_log_channel(guild_id, channel_id)
+                .map_err(|error| CommandError::failed("Could not save log channel!", error))?;
+        }
+
+        if let Some(category) = context.get_string_option("category", data) {
+            let Some(category) = AuditCategory::from_name(&category) else {
+                return Err(CommandError::Validation("Unknown log category".into()));
+            };
+            let enabled = context.get_boolean_option("enabled", data).unwrap_or(true);
+
+            self.database
+                .set_log_category_enabled(guild_id, category.as_str(), enabled)
+                .map_err(|error| CommandError::failed("Could not save log category!", error))?;
+        }
+
+        let channel = match self.database.get_log_channel(guild_id) {
+            Some(channel_id) => format!("<#{}>", channel_id),
+            None => "none".to_string(),
+        };
+        let categories = AuditCategory::ALL
+            .iter()
+            .map(|category| {
+                let enabled = self
+                    .database
+                    .is_log_category_enabled(guild_id, category.as_str());
+                format!(
+                    "{} `{}`",
+                    if enabled { "✅" } else { "❌" },
+                    category.as_str()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        context
+            .reply_ephemeral(format!("Log channel: {}\n{}", channel, categories))
+            .await
+    }
+}
+
+/// The audit trail of a user or guild as pages, each page is its own query
+pub struct AuditTrailPages {
+    database: Arc<Database>,
+    scope: AuditScope,
+    title: String,
+}
+
+impl AuditTrailPages {
+    pub fn new(database: Arc<Database>, scope: AuditScope, title: impl Into<String>) -> Self {
+        AuditTrailPages {
+            database,
+            scope,
+            title: title.into(),
+        }
+    }
+
+    fn line(&self, entry: &LinkAuditEntry) -> String {
+        let subject = match self.scope {
+            AuditScope::User(_) | AuditScope::UserInGuild(..) => String::new(),
+            AuditScope::Guild(_) => format!("<@{}> ", entry.discord_user),
+        };
+
+        format!(
+            "<t:{}:R> `{}` {}{}",
+            entry.created_at, entry.action, subject, entry.detail
+        )
+    }
+}
+
+#[async_trait]
+impl PageSource for AuditTrailPages {
+    async fn page_count(&self) -> Result<usize, CommandError> {
+        Ok(self
+            .database
+            .count_link_audit(self.scope)
+            .div_ceil(ENTRIES_PER_PAGE))
+    }
+
+    async fn page(&self, index: usize, _page_count: usize) -> Result<Embed, CommandError> {
+        let entries = self.database.get_link_audit_page(
+            self.scope,
+            index * ENTRIES_PER_PAGE,
+            ENTRIES_PER_PAGE,
+        );
+
+        let lines: Vec<String> = entries.iter().map(|entry| self.line(entry)).collect();
+
+        Ok(EmbedBuilder::new()
+            .title(self.title.as_str())
+            .description(lines.join("\n"))
+            .build())
+    }
+}
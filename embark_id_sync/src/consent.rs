@@ -0,0 +1,177 @@
+use async_trait::async_trait;
+use common::commands::components::{ComponentHandler, CustomId};
+use common::commands::{CommandBundle, CommandContext, CommandError};
+use data::Database;
+use std::sync::Arc;
+use tracing::info;
+use twilight_model::application::command::{Command, CommandType};
+use twilight_model::application::interaction::application_command::CommandData;
+use twilight_model::application::interaction::message_component::MessageComponentInteractionData;
+use twilight_model::id::Id;
+use twilight_model::id::marker::GuildMarker;
+use twilight_util::builder::command::{BooleanBuilder, CommandBuilder, StringBuilder};
+
+use crate::messages::MessageId;
+use crate::{EmbarkIDSync, can_manage_guild};
+
+/// Custom id prefix of the "Apply my existing link here" DM button, the guild id is its argument
+pub const APPLY_LINK_PREFIX: &str = "apply_link";
+
+/// The user agreed to have their existing link applied in a guild
+pub struct ApplyLinkButton {
+    sync: EmbarkIDSync,
+}
+
+impl ApplyLinkButton {
+    pub fn new(sync: EmbarkIDSync) -> Self {
+        ApplyLinkButton { sync }
+    }
+}
+
+#[async_trait]
+impl ComponentHandler for ApplyLinkButton {
+    fn prefix(&self) -> &str {
+        APPLY_LINK_PREFIX
+    }
+
+    async fn handle(
+        &self,
+        context: &mut CommandContext,
+        custom_id: &CustomId,
+        _data: &MessageComponentInteractionData,
+    ) -> Result<(), CommandError> {
+        let sync = &self.sync;
+        let guild_id: Id<GuildMarker> = custom_id.get(0)?;
+        let Some(user_id) = context.get_user_id() else {
+            return Err(CommandError::Validation("Unknown user".into()));
+        };
+        let locale = context.get_effective_locale();
+
+        let (Some(user), Some(guild_settings)) = (
+            sync.database.get_user_by_discord_id(user_id),
+            sync.database.get_guild_settings(&guild_id),
+        ) else {
+            return context
+                .reply_ephemeral(sync.message(
+                    Some(guild_id),
+                    MessageId::ApplyLinkUnavailable,
+                    locale.as_ref(),
+                    &[],
+                ))
+                .await;
+        };
+
+        // Without the consent the link would be taken back again right away, e.g. by a strict
+        // role policy
+        sync.database
+            .add_consent(user_id, guild_id)
+            .map_err(|error| {
+                CommandError::failed(
+                    sync.message(
+                        Some(guild_id),
+                        MessageId::ApplyLinkFailed,
+                        locale.as_ref(),
+                        &[],
+                    ),
+                    error,
+                )
+            })?;
+
+        info!("{} consented to their link in {}", user_id, guild_id);
+
+        let content = if sync.sync_member(&context.context, &user, &guild_settings) {
+            sync.message(
+                Some(guild_id),
+                MessageId::ApplyLinkQueued,
+                locale.as_ref(),
+                &[("embark_id", &user.embark_id.to_string())],
+            )
+        } else {
+            sync.message(
+                Some(guild_id),
+                MessageId::ApplyLinkFailed,
+                locale.as_ref(),
+                &[],
+            )
+        };
+
+        context.reply_ephemeral(content).await
+    }
+}
+
+pub struct ConsentCommand {
+    database: Arc<Database>,
+}
+
+impl ConsentCommand {
+    pub fn new(database: Arc<Database>) -> Self {
+        ConsentCommand { database }
+    }
+}
+
+#[async_trait]
+impl CommandBundle for ConsentCommand {
+    fn definition(&self) -> Command {
+        CommandBuilder::new(
+            "consent",
+            "Ask before an existing Embark ID link is applied in a server",
+            CommandType::ChatInput,
+        )
+        .option(BooleanBuilder::new("required", "Whether consent is required").required(true))
+        .option(
+            StringBuilder::new("scope", "Change it for yourself or for this server")
+                .choices([("Me", "me"), ("This server", "server")])
+                .required(false),
+        )
+        .build()
+    }
+
+    async fn execute(
+        &self,
+        context: &mut CommandContext,
+        data: &CommandData,
+    ) -> Result<(), CommandError> {
+        let Some(required) = context.get_boolean_option("required", data) else {
+            return Err(CommandError::Validation("Missing required option".into()));
+        };
+
+        match context.get_string_option("scope", data).as_deref() {
+            Some("server") => {
+                let Some(guild_id) = context.get_guild_id() else {
+                    return Err(CommandError::Validation(
+                        "This command must be done in a guild!".into(),
+                    ));
+                };
+
+                if !can_manage_guild(context.get_member_permissions()) {
+                    return context
+                        .reply_ephemeral("You need the Manage Server permission for this")
+                        .await;
+                }
+
+                self.database
+                    .set_guild_requires_consent(guild_id, required)
+                    .map_err(|error| {
+                        CommandError::failed("Could not save consent setting!", error)
+                    })?;
+            }
+            _ => {
+                let Some(user_id) = context.get_user_id() else {
+                    return Err(CommandError::Internal("Interaction without a user".into()));
+                };
+
+                self.database
+                    .set_user_requires_consent(user_id, required)
+                    .map_err(|error| {
+                        CommandError::failed("Could not save consent setting!", error)
+                    })?;
+            }
+        }
+
+        let state = if required { "required" } else { "not required" };
+
+        context
+            .reply_ephemeral(format!("Consent is now {}", state))
+            .await
+    }
+}
@@ -0,0 +1,336 @@
+use async_trait::async_trait;
+use common::commands::access::CommandAccess;
+use common::commands::{CommandBundle, CommandContext, CommandError, Locale};
+use data::{Database, DeliveryChannel, DmFallbackPolicy};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, error, info};
+use twilight_http::error::ErrorType;
+use twilight_model::application::command::{Command, CommandType};
+use twilight_model::application::interaction::application_command::CommandData;
+use twilight_model::channel::message::AllowedMentions;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{GuildMarker, UserMarker};
+use twilight_util::builder::command::{CommandBuilder, IntegerBuilder, StringBuilder};
+
+use crate::context::Context;
+use crate::messages::{MessageId, guild_message};
+use crate::{manage_guild_access, managed_guild};
+
+/// Users that could not be DMed are tried again after a week
+const DM_RETRY_AFTER_SECS: u64 = 7 * 24 * 60 * 60;
+const DIGEST_INTERVAL_SECS: u64 = 24 * 60 * 60;
+/// How often due digests and expired mentions are looked for
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(60);
+/// Keep digest messages under Discord's 2000 character limit
+const DIGEST_MESSAGE_LENGTH: usize = 1900;
+
+/// Whether DMs to this user recently failed, so they should not be tried again yet
+pub fn dms_closed(database: &Database, user_id: Id<UserMarker>) -> bool {
+    let Some((channel, updated_at)) = database.get_dm_delivery(user_id) else {
+        return false;
+    };
+
+    channel != DeliveryChannel::Dm && unix_now().saturating_sub(updated_at) < DM_RETRY_AFTER_SECS
+}
+
+/// Reach a member that can not be DMed in the way the guild chose
+///
+/// The verification channel is public, so the DM itself is never posted there. It can contain
+/// the member's Embark ID, which their privacy and consent settings may keep from the guild.
+pub async fn deliver_fallback(
+    context: &Arc<Context>,
+    database: &Database,
+    guild_id: Id<GuildMarker>,
+    user_id: Id<UserMarker>,
+) {
+    let Some(guild_settings) = database.get_guild_settings(&guild_id) else {
+        return;
+    };
+    let settings = database.get_dm_fallback_settings(guild_id);
+
+    let mut delivered = None;
+
+    if settings.policy == DmFallbackPolicy::Mention {
+        let allowed_mentions = AllowedMentions {
+            users: vec![user_id],
+            ..Default::default()
+        };
+        let locale = context
+            .cache()
+            .guild(guild_id)
+            .and_then(|guild| Locale::from_discord(guild.preferred_locale()));
+        let content = format!(
+            "<@{}> {}",
+            user_id,
+            guild_message(
+                database,
+                Some(guild_id),
+                MessageId::FallbackMention,
+                locale.as_ref(),
+                &[],
+            )
+        );
+
+        let message = match context
+            .client
+            .create_message(guild_settings.verification_channel)
+            .content(&content)
+            .allowed_mentions(Some(&allowed_mentions))
+            .await
+        {
+            Ok(response) => response.model().await.ok(),
+            Err(error) => {
+                error!("Could not mention {} in {}: {}", user_id, guild_id, error);
+                None
+            }
+        };
+
+        if let Some(message) = message {
+            let delete_at = unix_now() + settings.delete_after * 60;
+            if let Err(error) =
+                database.schedule_deletion(message.channel_id, message.id, delete_at)
+            {
+                error!(
+                    "Could not schedule deleting fallback mention {}: {}",
+                    message.id, error
+                );
+            }
+
+            delivered = Some(DeliveryChannel::Mention);
+        }
+    }
+
+    // The digest is also the last resort when the mention could not be posted
+    if delivered.is_none() && settings.policy != DmFallbackPolicy::Nothing {
+        match database.add_to_dm_digest(guild_id, user_id) {
+            Ok(()) => delivered = Some(DeliveryChannel::Digest),
+            Err(error) => error!("Could not add {} to the digest: {}", user_id, error),
+        }
+    }
+
+    let Some(delivered) = delivered else {
+        return;
+    };
+
+    info!(
+        "Reached {} in {} by {}",
+        user_id,
+        guild_id,
+        delivered.as_str()
+    );
+
+    if let Err(error) = database.set_dm_delivery(user_id, delivered) {
+        error!("Could not save delivery channel of {}: {}", user_id, error);
+    }
+}
+
+/// Posts the digests that are due and deletes fallback mentions that expired
+///
+/// Both are kept in the database, so a restart neither starts a digest's day over nor leaves
+/// mentions up.
+pub async fn run_fallback_upkeep(context: Arc<Context>, database: Arc<Database>) {
+    loop {
+        delete_expired_mentions(&context, &database).await;
+        post_due_digests(&context, &database).await;
+
+        tokio::time::sleep(UPKEEP_INTERVAL).await;
+    }
+}
+
+async fn delete_expired_mentions(context: &Context, database: &Database) {
+    for (channel_id, message_id) in database.get_due_deletions() {
+        match context.client.delete_message(channel_id, message_id).await {
+            Ok(_) => {}
+            // Already deleted by someone else, or the bot lost access to the channel
+            Err(error)
+                if matches!(
+                    error.kind(),
+                    ErrorType::Response { status, .. } if status.is_client_error()
+                ) =>
+            {
+                debug!(
+                    "Could not delete fallback mention {}: {}",
+                    message_id, error
+                );
+            }
+            Err(error) => {
+                error!(
+                    "Could not delete fallback mention {}, trying again later: {}",
+                    message_id, error
+                );
+                continue;
+            }
+        }
+
+        if let Err(error) = database.remove_deletion(message_id) {
+            error!("Could not remove deletion of {}: {}", message_id, error);
+        }
+    }
+}
+
+/// Post each guild's digest once a day in its verification channel
+///
+/// Members are only taken out of the digest once it was posted, a failed post is tried again.
+async fn post_due_digests(context: &Context, database: &Database) {
+    let now = unix_now();
+
+    for guild_id in database.get_dm_digest_guilds() {
+        match database.get_last_dm_digest(guild_id) {
+            Some(posted_at) if now.saturating_sub(posted_at) >= DIGEST_INTERVAL_SECS => {}
+            Some(_) => continue,
+            None => {
+                // The guild's first digest, its day starts now
+                if let Err(error) = database.set_last_dm_digest(guild_id, now) {
+                    error!("Could not save digest time of {}: {}", guild_id, error);
+                }
+                continue;
+            }
+        }
+
+        let Some(guild_settings) = database.get_guild_settings(&guild_id) else {
+            continue;
+        };
+
+        let users = database.get_dm_digest(guild_id);
+        if users.is_empty() {
+            continue;
+        }
+
+        let allowed_mentions = AllowedMentions {
+            users: users.clone(),
+            ..Default::default()
+        };
+
+        let locale = context
+            .cache()
+            .guild(guild_id)
+            .and_then(|guild| Locale::from_discord(guild.preferred_locale()));
+        let mut messages = vec![guild_message(
+            database,
+            Some(guild_id),
+            MessageId::DigestHeader,
+            locale.as_ref(),
+            &[],
+        )];
+        for user_id in &users {
+            let mention = format!(" <@{}>", user_id);
+
+            if messages.last().map_or(0, String::len) + mention.len() > DIGEST_MESSAGE_LENGTH {
+                messages.push(String::new());
+            }
+            if let Some(message) = messages.last_mut() {
+                message.push_str(&mention);
+            }
+        }
+
+        let mut posted = true;
+        for message in messages {
+            if let Err(error) = context
+                .client
+                .create_message(guild_settings.verification_channel)
+                .content(&message)
+                .allowed_mentions(Some(&allowed_mentions))
+                .await
+            {
+                error!("Could not post the digest of {}: {}", guild_id, error);
+                posted = false;
+                break;
+            }
+        }
+        if !posted {
+            continue;
+        }
+
+        if let Err(error) = database.remove_from_dm_digest(guild_id, &users) {
+            error!("Could not clear the digest of {}: {}", guild_id, error);
+        }
+        if let Err(error) = database.set_last_dm_digest(guild_id, now) {
+            error!("Could not save digest time of {}: {}", guild_id, error);
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+pub struct DmFallbackCommand {
+    database: Arc<Database>,
+}
+
+impl DmFallbackCommand {
+    pub fn new(database: Arc<Database>) -> Self {
+        DmFallbackCommand { database }
+    }
+}
+
+#[async_trait]
+impl CommandBundle for DmFallbackCommand {
+    fn definition(&self) -> Command {
+        CommandBuilder::new(
+            "dmfallback",
+            "Choose how members with closed DMs are reached",
+            CommandType::ChatInput,
+        )
+        .option(
+            StringBuilder::new("policy", "What to do when a DM fails")
+                .choices([
+                    (
+                        "Mention them in the verification channel",
+                        DmFallbackPolicy::Mention.as_str(),
+                    ),
+                    (
+                        "Add them to a daily digest",
+                        DmFallbackPolicy::Digest.as_str(),
+                    ),
+                    ("Nothing", DmFallbackPolicy::Nothing.as_str()),
+                ])
+                .required(true),
+        )
+        .option(
+            IntegerBuilder::new("delete_after", "Minutes before a mention is deleted again")
+                .min_value(1)
+                .max_value(24 * 60)
+                .required(false),
+        )
+        .build()
+    }
+
+    fn access(&self) -> CommandAccess {
+        manage_guild_access()
+    }
+
+    async fn execute(
+        &self,
+        context: &mut CommandContext,
+        data: &CommandData,
+    ) -> Result<(), CommandError> {
+        let guild_id = managed_guild(context)?;
+
+        let mut settings = self.database.get_dm_fallback_settings(guild_id);
+
+        if let Some(policy) = context.get_string_option("policy", data) {
+            settings.policy = DmFallbackPolicy::from_name(&policy)
+                .ok_or_else(|| CommandError::Validation("Unknown fallback policy".into()))?;
+        }
+        if let Some(delete_after) = context.get_integer_option("delete_after", data) {
+            settings.delete_after = delete_after.max(1) as u64;
+        }
+
+        self.database
+            .set_dm_fallback_settings(&settings)
+            .map_err(|error| CommandError::failed("Could not save DM fallback settings!", error))?;
+
+        context
+            .reply_ephemeral(format!(
+                "DM fallback: `{}`, mentions are deleted after {} minutes",
+                settings.policy.as_str(),
+                settings.delete_after
+            ))
+            .await
+    }
+}
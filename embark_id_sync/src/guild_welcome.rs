@@ -33,6 +33,8 @@ use twilight_gateway::Event;
 
 use crate::EmbarkIDSync;
 use crate::context::Context;
+use crate::messages::MessageId;
+use common::commands::Locale;
 
 impl EmbarkIDSync {
     pub async fn guild_event(&self, context: Arc<Context>, guild_create: &Box<GuildCreate>) {
@@ -78,7 +80,12 @@ impl EmbarkIDSync {
                         if let Err(error) = context
                             .client
                             .create_message(channel_id)
-                            .content("Please run /setup to get started.")
+                            .content(&self.message(
+                                Some(guild.id),
+                                MessageId::SetupPrompt,
+                                Locale::from_discord(&guild.preferred_locale).as_ref(),
+                                &[],
+                            ))
                             .await
                         {
                             error!("Error sending first time message: {:?}", error);
@@ -0,0 +1,376 @@
+use async_trait::async_trait;
+use common::commands::access::CommandAccess;
+use common::commands::{CommandBundle, CommandContext, CommandError};
+use data::{Database, DeliveryChannel, Job, JobKind};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
+use twilight_http::api_error::ApiError;
+use twilight_http::error::ErrorType;
+use twilight_model::application::command::{Command, CommandType};
+use twilight_model::application::interaction::application_command::CommandData;
+use twilight_model::channel::message::Component;
+use twilight_model::id::Id;
+use twilight_model::id::marker::UserMarker;
+use twilight_util::builder::command::CommandBuilder;
+
+use crate::audit_log::{AuditCategory, AuditEvent, AuditLog};
+use crate::context::Context;
+use crate::dm_fallback::{deliver_fallback, dms_closed};
+use crate::{manage_guild_access, managed_guild};
+
+/// How often the outbox is checked when it is empty
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const JOBS_PER_TICK: u32 = 20;
+/// Jobs that still fail after this many attempts are given up on
+const MAX_ATTEMPTS: u32 = 8;
+const BASE_RETRY_DELAY_SECS: u64 = 5;
+const MAX_RETRY_DELAY_SECS: u64 = 60 * 60;
+/// Longest job error shown by /diagnose
+const MAX_ERROR_LENGTH: usize = 300;
+
+enum JobError {
+    /// Will never succeed, such as missing permissions or an unknown member
+    Permanent(String),
+    /// Rate limits, server errors and network problems
+    Transient {
+        message: String,
+        retry_after: Option<Duration>,
+    },
+}
+
+impl From<twilight_http::Error> for JobError {
+    fn from(error: twilight_http::Error) -> Self {
+        let message = error.to_string();
+
+        match error.kind() {
+            ErrorType::Response {
+                error: ApiError::Ratelimited(ratelimited),
+                ..
+            } => JobError::Transient {
+                message,
+                retry_after: Some(Duration::from_secs_f64(ratelimited.retry_after)),
+            },
+            ErrorType::Response { status, .. }
+                if status.get() == 429 || status.is_server_error() =>
+            {
+                JobError::Transient {
+                    message,
+                    retry_after: None,
+                }
+            }
+            ErrorType::Response { .. } => JobError::Permanent(message),
+            _ => JobError::Transient {
+                message,
+                retry_after: None,
+            },
+        }
+    }
+}
+
+/// Cut a job error short, so five of them fit in one message
+fn truncate_error(error: &str) -> String {
+    if error.chars().count() <= MAX_ERROR_LENGTH {
+        return error.to_string();
+    }
+
+    let mut truncated: String = error.chars().take(MAX_ERROR_LENGTH - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Exponential backoff: 5s, 10s, 20s, ... capped at an hour
+fn retry_delay(attempts: u32) -> u64 {
+    (BASE_RETRY_DELAY_SECS << attempts.min(16)).min(MAX_RETRY_DELAY_SECS)
+}
+
+/// Executes the Discord side effects queued in the outbox
+pub struct JobWorker {
+    database: Arc<Database>,
+    audit_log: Arc<AuditLog>,
+}
+
+impl JobWorker {
+    pub fn new(database: Arc<Database>, audit_log: Arc<AuditLog>) -> Self {
+        JobWorker {
+            database,
+            audit_log,
+        }
+    }
+
+    pub async fn run(self, context: Arc<Context>) {
+        info!("Starting job worker");
+
+        loop {
+            let jobs = self.database.get_due_jobs(JOBS_PER_TICK);
+
+            if jobs.is_empty() {
+                tokio::time::sleep(POLL_INTERVAL).await;
+                continue;
+            }
+
+            for job in jobs {
+                // Back off from Discord entirely while we are rate limited
+                if let Some(retry_after) = self.process(&context, job).await {
+                    debug!("Rate limited, pausing job worker for {:?}", retry_after);
+                    tokio::time::sleep(retry_after).await;
+                }
+            }
+        }
+    }
+
+    /// Returns how long to wait when the job hit a rate limit
+    async fn process(&self, context: &Arc<Context>, job: Job) -> Option<Duration> {
+        // Don't keep trying DMs we know will fail, go straight to the fallback
+        if let JobKind::SendDm {
+            guild_id: Some(guild_id),
+            discord_user,
+            ..
+        }
+        | JobKind::SendDmWithComponents {
+            guild_id: Some(guild_id),
+            discord_user,
+            ..
+        } = &job.kind
+        {
+            if dms_closed(&self.database, *discord_user) {
+                debug!("DMs of {} are closed, using fallback", discord_user);
+                deliver_fallback(context, &self.database, *guild_id, *discord_user).await;
+                self.complete(&job);
+                return None;
+            }
+        }
+
+        let (message, retry_after) = match execute(context, &job.kind).await {
+            Ok(()) => {
+                debug!("Completed {} job {}", job.kind.as_str(), job.id);
+                if let JobKind::SendDm { discord_user, .. }
+                | JobKind::SendDmWithComponents { discord_user, .. } = &job.kind
+                {
+                    if let Err(error) = self
+                        .database
+                        .set_dm_delivery(*discord_user, DeliveryChannel::Dm)
+                    {
+                        error!("Could not save delivery channel: {}", error);
+                    }
+                }
+                self.complete(&job);
+                return None;
+            }
+            Err(JobError::Permanent(message)) => {
+                warn!("{} job {} failed: {}", job.kind.as_str(), job.id, message);
+                self.give_up(context, &job, &message).await;
+                return None;
+            }
+            Err(JobError::Transient {
+                message,
+                retry_after,
+            }) => (message, retry_after),
+        };
+
+        if job.attempts + 1 >= MAX_ATTEMPTS {
+            warn!(
+                "{} job {} failed {} times: {}",
+                job.kind.as_str(),
+                job.id,
+                MAX_ATTEMPTS,
+                message
+            );
+            self.give_up(context, &job, &message).await;
+            return retry_after;
+        }
+
+        let delay = retry_after
+            .map(|retry_after| retry_after.as_secs().max(1))
+            .unwrap_or_else(|| retry_delay(job.attempts));
+
+        debug!(
+            "Retrying {} job {} in {}s: {}",
+            job.kind.as_str(),
+            job.id,
+            delay,
+            message
+        );
+        if let Err(error) = self.database.retry_job(job.id, delay, &message) {
+            error!("Could not reschedule job {}: {}", job.id, error);
+        }
+
+        retry_after
+    }
+
+    fn complete(&self, job: &Job) {
+        if let Err(error) = self.database.complete_job(job.id) {
+            error!("Could not complete job {}: {}", job.id, error);
+        }
+    }
+
+    async fn give_up(&self, context: &Arc<Context>, job: &Job, message: &str) {
+        if let Err(error) = self.database.fail_job(job.id, message) {
+            error!("Could not mark job {} as failed: {}", job.id, error);
+        }
+
+        // Closed DMs are expected, reach the member some other way instead of reporting it
+        if let JobKind::SendDm {
+            guild_id: Some(guild_id),
+            discord_user,
+            ..
+        }
+        | JobKind::SendDmWithComponents {
+            guild_id: Some(guild_id),
+            discord_user,
+            ..
+        } = &job.kind
+        {
+            deliver_fallback(context, &self.database, *guild_id, *discord_user).await;
+            return;
+        }
+
+        self.audit_log.record(
+            &self.database,
+            context,
+            job.kind.guild_id(),
+            AuditEvent::new(
+                AuditCategory::UpdateFailure,
+                job.kind.discord_user(),
+                format!("could not be updated ({})", job.kind.as_str()),
+            )
+            .field("Error", message),
+        );
+    }
+}
+
+async fn execute(context: &Context, kind: &JobKind) -> Result<(), JobError> {
+    match kind {
+        JobKind::AddRole {
+            guild_id,
+            discord_user,
+            role_id,
+        } => {
+            context
+                .client
+                .add_guild_member_role(*guild_id, *discord_user, *role_id)
+                .await?;
+        }
+        JobKind::RemoveRole {
+            guild_id,
+            discord_user,
+            role_id,
+        } => {
+            context
+                .client
+                .remove_guild_member_role(*guild_id, *discord_user, *role_id)
+                .await?;
+        }
+        JobKind::SetNickname {
+            guild_id,
+            discord_user,
+            nickname,
+        } => {
+            context
+                .client
+                .update_guild_member(*guild_id, *discord_user)
+                .nick(nickname.as_deref())
+                .await?;
+        }
+        JobKind::SendDm {
+            discord_user,
+            content,
+            ..
+        } => {
+            send_dm(context, *discord_user, content, &[]).await?;
+        }
+        JobKind::SendDmWithComponents {
+            discord_user,
+            content,
+            components,
+            ..
+        } => {
+            send_dm(context, *discord_user, content, components).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn send_dm(
+    context: &Context,
+    user_id: Id<UserMarker>,
+    content: &str,
+    components: &[Component],
+) -> Result<(), JobError> {
+    let dm_channel = context
+        .client
+        .create_private_channel(user_id)
+        .await?
+        .model()
+        .await
+        .map_err(|error| JobError::Transient {
+            message: error.to_string(),
+            retry_after: None,
+        })?;
+
+    context
+        .client
+        .create_message(dm_channel.id)
+        .content(content)
+        .components(components)
+        .await?;
+
+    Ok(())
+}
+
+pub struct DiagnoseCommand {
+    database: Arc<Database>,
+}
+
+impl DiagnoseCommand {
+    pub fn new(database: Arc<Database>) -> Self {
+        DiagnoseCommand { database }
+    }
+}
+
+#[async_trait]
+impl CommandBundle for DiagnoseCommand {
+    fn definition(&self) -> Command {
+        CommandBuilder::new(
+            "diagnose",
+            "Show the state of pending role and nickname updates",
+            CommandType::ChatInput,
+        )
+        .build()
+    }
+
+    fn access(&self) -> CommandAccess {
+        manage_guild_access()
+    }
+
+    async fn execute(
+        &self,
+        context: &mut CommandContext,
+        _data: &CommandData,
+    ) -> Result<(), CommandError> {
+        let guild_id = managed_guild(context)?;
+
+        let depth = self.database.get_job_queue_depth(Some(guild_id));
+
+        let mut content = format!(
+            "**Job queue**\n{} pending, {} failed",
+            depth.pending, depth.failed
+        );
+
+        let failed_jobs = self.database.get_failed_jobs(guild_id, 5);
+        if !failed_jobs.is_empty() {
+            content.push_str("\n\n**Recent failures**");
+            for job in failed_jobs {
+                content.push_str(&format!(
+                    "\n`{}` for <@{}>: {}",
+                    job.kind.as_str(),
+                    job.kind.discord_user(),
+                    truncate_error(job.last_error.as_deref().unwrap_or("unknown error"))
+                ));
+            }
+        }
+
+        context.reply_ephemeral(content).await
+    }
+}
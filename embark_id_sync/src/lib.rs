@@ -3,46 +3,130 @@ use common::commands::CommandBundle;
 use common::commands::CommandContext;
 use common::commands::CommandError;
 use common::commands::CommandRegistration;
-use data::EmbarkID;
+use common::commands::Locale;
+use common::commands::LocalizedText;
+use common::commands::access::CommandAccess;
+use common::commands::components::{ComponentHandler, CustomId, ModalHandler};
+use common::commands::cooldowns::{Cooldown, CooldownBucket};
+use common::commands::definition::LocalizedCommandBuilder;
 use data::GuildSettings;
+use data::JobKind;
 use data::User;
+use rusqlite::Result as SqliteResult;
 use twilight_http::Client;
 use twilight_model::application::command::Command;
 use twilight_model::application::command::CommandType;
 use twilight_model::application::interaction::application_command::CommandData;
-use twilight_model::application::interaction::{InteractionData, InteractionType};
 use twilight_model::channel::ChannelType;
-use twilight_model::channel::message::component::{ActionRow, TextInput, TextInputStyle};
-use twilight_model::channel::message::{Component, MessageFlags};
+use twilight_model::channel::message::Component;
+use twilight_model::channel::message::component::ButtonStyle;
 use twilight_model::channel::permission_overwrite::{PermissionOverwrite, PermissionOverwriteType};
 use twilight_model::guild::Permissions;
-use twilight_model::http::interaction::{
-    InteractionResponse, InteractionResponseData, InteractionResponseType,
-};
 use twilight_model::id::Id;
 use twilight_model::id::marker::GuildMarker;
-use twilight_model::id::marker::InteractionMarker;
-use twilight_util::builder::command::CommandBuilder;
+use twilight_model::id::marker::UserMarker;
 use twilight_util::builder::message::{ActionRowBuilder, ButtonBuilder};
 
 use common::context;
 use common::handler::Handler;
 use data::Database;
 use std::sync::Arc;
-use tracing::{debug, error, info, warn};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::{error, info};
 use twilight_gateway::Event;
 use twilight_util::permission_calculator::PermissionCalculator;
 
+use crate::admin::admin_command;
+use crate::audit_log::{AuditCategory, AuditEvent, AuditLog, LogChannelCommand};
+use crate::consent::{APPLY_LINK_PREFIX, ApplyLinkButton, ConsentCommand};
 use crate::context::Context;
+use crate::dm_fallback::{DmFallbackCommand, run_fallback_upkeep};
+use crate::jobs::{DiagnoseCommand, JobWorker};
+use crate::messages::{guild_message, messages_command};
+use crate::nickname_lock::{NicknameLockCommand, NicknameLockState};
+use crate::privacy::PrivacyCommand;
+use crate::role_sync::RolePolicyCommand;
+use crate::verification::{VERIFY_PREFIX, VerifyButton, VerifyModal};
+use crate::whois::WhoisCommand;
+mod admin;
+mod audit_log;
+mod consent;
+mod dm_fallback;
 mod guild_welcome;
+mod jobs;
+mod messages;
+mod nickname_lock;
+mod privacy;
+mod role_sync;
+mod verification;
+mod whois;
 
+pub use crate::messages::{MessageId, translations};
+
+#[derive(Clone)]
 pub struct EmbarkIDSync {
     database: Arc<Database>,
+    audit_log: Arc<AuditLog>,
+    nickname_lock: Arc<NicknameLockState>,
+    job_worker_started: Arc<AtomicBool>,
 }
 
 impl EmbarkIDSync {
-    pub async fn new(database: Arc<Database>) -> Self {
-        EmbarkIDSync { database }
+    pub fn new(database: Arc<Database>) -> Self {
+        EmbarkIDSync {
+            database,
+            audit_log: Arc::new(AuditLog::default()),
+            nickname_lock: Arc::new(NicknameLockState::default()),
+            job_worker_started: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Queue the updates that apply a link in a guild, returns whether they were queued
+    pub fn sync_member(
+        &self,
+        context: &Arc<Context>,
+        user: &User,
+        guild_settings: &GuildSettings,
+    ) -> bool {
+        let Err(error) = update_user(&self.database, user, guild_settings) else {
+            return true;
+        };
+
+        error!(
+            "Could not queue update for {}: {}",
+            user.discord_user, error
+        );
+        self.audit(
+            context,
+            Some(guild_settings.guild_id),
+            AuditEvent::new(
+                AuditCategory::UpdateFailure,
+                user.discord_user,
+                "could not be given the verified role or nickname",
+            )
+            .field("Embark ID", user.embark_id.to_string()),
+        );
+
+        false
+    }
+
+    /// Queue a DM, it is retried by the job worker when Discord has trouble
+    pub fn queue_dm(
+        &self,
+        guild_id: Option<Id<GuildMarker>>,
+        user_id: Id<UserMarker>,
+        content: String,
+    ) {
+        let job = JobKind::SendDm {
+            guild_id,
+            discord_user: user_id,
+            content,
+        };
+
+        if let Err(error) = self.database.enqueue_job(&job) {
+            error!("Could not queue DM to {}: {}", user_id, error);
+        }
     }
 }
 
@@ -50,6 +134,15 @@ impl EmbarkIDSync {
 impl Handler for EmbarkIDSync {
     async fn handle(&self, context: Arc<Context>, event: Arc<Event>) {
         match &*event {
+            Event::Ready(_) => {
+                // Ready is sent again on reconnects, only start one worker
+                if !self.job_worker_started.swap(true, Ordering::SeqCst) {
+                    let job_worker =
+                        JobWorker::new(Arc::clone(&self.database), Arc::clone(&self.audit_log));
+                    tokio::spawn(job_worker.run(Arc::clone(&context)));
+                    tokio::spawn(run_fallback_upkeep(context, Arc::clone(&self.database)));
+                }
+            }
             Event::MemberAdd(member_add) => {
                 let user = &member_add.user;
                 let guild_id = member_add.guild_id;
@@ -63,204 +156,180 @@ impl Handler for EmbarkIDSync {
                     .guild(guild_id)
                     .and_then(|cached_guild| Some(cached_guild.name().to_string()))
                     .unwrap_or("a guild".to_string());
+                let locale = context
+                    .cache()
+                    .guild(guild_id)
+                    .and_then(|cached_guild| Locale::from_discord(cached_guild.preferred_locale()));
 
                 match self.database.get_user_by_discord_id(user.id) {
                     None => {
                         // TODO: DM the user
-                        context.send_dm_to_user(user.id,format!("`{}` uses this bot for Embark ID linking.\nPlease go to <#{}> and follow the instructions to link your account.", guild_name, guild_config.verification_channel).as_str()).await;
+                        let message = self.message(
+                            Some(guild_id),
+                            MessageId::JoinNotLinked,
+                            locale.as_ref(),
+                            &[
+                                ("guild", &guild_name),
+                                (
+                                    "channel",
+                                    &format!("<#{}>", guild_config.verification_channel),
+                                ),
+                            ],
+                        );
+                        self.queue_dm(Some(guild_id), user.id, message);
                     }
                     Some(database_user) => {
-                        update_user(&context.client(), &database_user, &guild_config).await;
+                        // Ask first instead of publishing the link in a guild the user never agreed to
+                        if self.database.needs_consent(user.id, guild_id) {
+                            let apply_button = Component::ActionRow(
+                                ActionRowBuilder::new()
+                                    .component(
+                                        ButtonBuilder::new(ButtonStyle::Primary)
+                                            .label(self.message(
+                                                Some(guild_id),
+                                                MessageId::ApplyLinkButton,
+                                                locale.as_ref(),
+                                                &[],
+                                            ))
+                                            .custom_id(
+                                                CustomId::new(APPLY_LINK_PREFIX)
+                                                    .arg(guild_id)
+                                                    .to_string(),
+                                            )
+                                            .build(),
+                                    )
+                                    .build(),
+                            );
+
+                            let prompt = JobKind::SendDmWithComponents {
+                                guild_id: Some(guild_id),
+                                discord_user: user.id,
+                                content: self.message(
+                                    Some(guild_id),
+                                    MessageId::ApplyLinkPrompt,
+                                    locale.as_ref(),
+                                    &[
+                                        ("guild", &guild_name),
+                                        ("embark_id", &database_user.embark_id.to_string()),
+                                    ],
+                                ),
+                                components: vec![apply_button],
+                            };
+                            if let Err(error) = self.database.enqueue_job(&prompt) {
+                                error!("Could not queue consent prompt to {}: {}", user.id, error);
+                            }
+
+                            return;
+                        }
+
+                        self.sync_member(&context, &database_user, &guild_config);
 
                         if let Some(other_guilds) = context.cache().user_guilds(user.id) {
                             let guild_settings_list: Vec<GuildSettings> = other_guilds
                                 .value()
                                 .iter()
+                                .filter(|guild_id| {
+                                    !self.database.needs_consent(user.id, **guild_id)
+                                })
                                 .filter_map(|guild_id| self.database.get_guild_settings(guild_id))
                                 .collect();
 
                             for guild_settings in guild_settings_list {
-                                update_user(&context.client(), &database_user, &guild_settings)
-                                    .await;
+                                self.sync_member(&context, &database_user, &guild_settings);
                             }
                         }
 
-                        context.send_dm_to_user(user.id,format!(
-                                             "`{}` uses this bot for Embark ID linking.
-                                             \nSince your have already linked your account (`{}`) there is nothing that you need to do. GLHF Contestant!",
-                                             guild_name,
-                                             database_user.embark_id.to_string()).as_str())
-                                     .await;
+                        let message = self.message(
+                            Some(guild_id),
+                            MessageId::JoinAlreadyLinked,
+                            locale.as_ref(),
+                            &[
+                                ("guild", &guild_name),
+                                ("embark_id", &database_user.embark_id.to_string()),
+                            ],
+                        );
+                        self.queue_dm(Some(guild_id), user.id, message);
                     }
                 }
             }
+            Event::MemberUpdate(member_update) => {
+                self.sync_verified_role(
+                    &context,
+                    member_update.guild_id,
+                    member_update.user.id,
+                    &member_update.roles,
+                )
+                .await;
+                self.enforce_nickname_lock(context, member_update).await;
+            }
+            Event::MemberRemove(member_remove) => {
+                self.member_remove(member_remove).await;
+            }
             Event::GuildCreate(guild_create) => {
                 self.guild_event(context, guild_create).await;
             }
-            Event::InteractionCreate(interaction) => {
-                let Some(data) = &interaction.data else {
-                    return;
-                };
-
-                match interaction.kind {
-                    InteractionType::MessageComponent => {
-                        let InteractionData::MessageComponent(message_component) = data else {
-                            return;
-                        };
-
-                        info!("message component");
-
-                        let extra_length = 5; // this is for #1234
-                        let min_length = Some(2 + extra_length); // according to embark's website min characters is 2
-                        let max_length = Some(16 + extra_length); // max characters is 16
-
-                        if message_component.custom_id == "verify" {
-                            let embark_id_input = Component::TextInput(TextInput {
-                                id: None,
-                                custom_id: "embark_verification".to_string(),
-                                label: "EMBARKID".to_string(),
-                                max_length,
-                                min_length,
-                                placeholder: Some("name#1234".to_string()),
-                                required: Some(true),
-                                style: TextInputStyle::Short,
-                                value: None,
-                            });
-
-                            let action_row = Component::ActionRow(ActionRow {
-                                id: None,
-                                components: vec![embark_id_input],
-                            });
-
-                            let data = InteractionResponseData {
-                                custom_id: Some("embark_verification".to_string()),
-                                title: Some("Provide Your EmbarkID".to_string()),
-                                components: Some(vec![action_row]),
-                                ..Default::default()
-                            };
-
-                            let response = InteractionResponse {
-                                kind: InteractionResponseType::Modal,
-                                data: Some(data),
-                            };
-
-                            context
-                                .client
-                                .interaction(context.application_id)
-                                .create_response(interaction.id, &interaction.token, &response)
-                                .await;
-                        }
-                    }
-                    InteractionType::ApplicationCommandAutocomplete => {}
-                    InteractionType::ApplicationCommand => {}
-                    InteractionType::ModalSubmit => {
-                        info!("ModalSubmit");
-
-                        let InteractionData::ModalSubmit(modal_submit) = data else {
-                            return;
-                        };
-
-                        if modal_submit.custom_id == "embark_verification" {
-                            for row in &modal_submit.components {
-                                for component in &row.components {
-                                    if component.custom_id == "embark_verification" {
-                                        info!("embark_verification");
-
-                                        let Some(discord_user) = &interaction
-                                            .member
-                                            .as_ref()
-                                            .and_then(|member| member.user.clone())
-                                        else {
-                                            return;
-                                        };
-                                        info!("valid user");
-
-                                        let Some(embark_id) = component
-                                            .value
-                                            .as_ref()
-                                            .and_then(|value| EmbarkID::new(&value).ok())
-                                        else {
-                                            reply_ephemeral(
-                                                &context,
-                                                interaction.id,
-                                                &interaction.token,
-                                                "Invalid EmbarkID".to_string(),
-                                            )
-                                            .await;
-
-                                            return;
-                                        };
-
-                                        if let Some(embark_user_profile) =
-                                            self.database.get_user_by_embark_id(&embark_id)
-                                        {
-                                            reply_ephemeral(
-                                                &context,
-                                                interaction.id,
-                                                &interaction.token,
-                                                "Someone has already claimed this EmbarkID".into(),
-                                            )
-                                            .await;
-
-                                            return;
-                                        }
-
-                                        let response = InteractionResponse {
-                                            kind: InteractionResponseType::ChannelMessageWithSource,
-                                            data: Some(InteractionResponseData {
-                                                content: Some(format!(
-                                                    "You entered: {}",
-                                                    embark_id.to_string()
-                                                )),
-                                                flags: Some(MessageFlags::EPHEMERAL),
-                                                ..Default::default()
-                                            }),
-                                        };
-
-                                        context
-                                            .client
-                                            .interaction(context.application_id)
-                                            .create_response(
-                                                interaction.id,
-                                                &interaction.token,
-                                                &response,
-                                            )
-                                            .await;
-
-                                        let user = User {
-                                            discord_user: discord_user.id,
-                                            embark_id: embark_id.clone(),
-                                        };
-
-                                        self.database.add_user(&user);
-
-                                        if let Some(guild_id) = interaction.guild_id {
-                                            let Some(guild_settings) =
-                                                self.database.get_guild_settings(&guild_id)
-                                            else {
-                                                return;
-                                            };
-
-                                            update_user(&context.client, &user, &guild_settings)
-                                                .await;
-                                        }
-                                    }
-                                }
-                            }
-                        };
-                    }
-                    _ => warn!("unknown interaction type"),
-                }
-            }
             _ => {}
         }
     }
 
+    fn components(&self) -> Vec<Box<dyn ComponentHandler>> {
+        vec![
+            Box::new(VerifyButton::new(self.clone())),
+            Box::new(ApplyLinkButton::new(self.clone())),
+        ]
+    }
+
+    fn modals(&self) -> Vec<Box<dyn ModalHandler>> {
+        vec![Box::new(VerifyModal::new(self.clone()))]
+    }
+
     fn commands(&self) -> Vec<CommandRegistration> {
-        vec![CommandRegistration {
-            scope: common::commands::CommandScope::Global,
-            command: Box::new(SetupCommand::new(Arc::clone(&self.database))),
-        }]
+        vec![
+            CommandRegistration {
+                scope: common::commands::CommandScope::Global,
+                command: Box::new(SetupCommand::new(Arc::clone(&self.database))),
+            },
+            CommandRegistration {
+                scope: common::commands::CommandScope::Global,
+                command: Box::new(PrivacyCommand::new(Arc::clone(&self.database))),
+            },
+            CommandRegistration {
+                scope: common::commands::CommandScope::Global,
+                command: Box::new(WhoisCommand::new(Arc::clone(&self.database))),
+            },
+            CommandRegistration {
+                scope: common::commands::CommandScope::Global,
+                command: Box::new(ConsentCommand::new(Arc::clone(&self.database))),
+            },
+            CommandRegistration {
+                scope: common::commands::CommandScope::Global,
+                command: Box::new(NicknameLockCommand::new(Arc::clone(&self.database))),
+            },
+            CommandRegistration {
+                scope: common::commands::CommandScope::Global,
+                command: Box::new(LogChannelCommand::new(Arc::clone(&self.database))),
+            },
+            CommandRegistration {
+                scope: common::commands::CommandScope::Global,
+                command: Box::new(RolePolicyCommand::new(Arc::clone(&self.database))),
+            },
+            CommandRegistration {
+                scope: common::commands::CommandScope::Global,
+                command: Box::new(DiagnoseCommand::new(Arc::clone(&self.database))),
+            },
+            CommandRegistration {
+                scope: common::commands::CommandScope::Global,
+                command: Box::new(DmFallbackCommand::new(Arc::clone(&self.database))),
+            },
+            CommandRegistration {
+                scope: common::commands::CommandScope::Global,
+                command: Box::new(messages_command(Arc::clone(&self.database))),
+            },
+            CommandRegistration {
+                scope: common::commands::CommandScope::Global,
+                command: Box::new(admin_command(self.clone())),
+            },
+        ]
     }
 }
 
@@ -275,7 +344,9 @@ impl SetupCommand {
 
     pub async fn setup_verification(
         context: &Arc<Context>,
+        database: &Database,
         guild_id: Id<GuildMarker>,
+        locale: Option<&Locale>,
     ) -> Result<GuildSettings, SetupErrors> {
         let everyone_role_id = Id::new(guild_id.get());
 
@@ -287,10 +358,10 @@ impl SetupCommand {
             .mentionable(false)
             .permissions(Permissions::empty())
             .await
-            .map_err(|_| SetupErrors::CouldNotCreateRole)?
+            .map_err(|error| SetupErrors::CouldNotCreateRole(error.to_string()))?
             .model()
             .await
-            .map_err(|_| SetupErrors::CouldNotCreateRole)?;
+            .map_err(|error| SetupErrors::CouldNotCreateRole(error.to_string()))?;
 
         info!("created role: {} ({})", role.name, role.id);
 
@@ -313,10 +384,10 @@ impl SetupCommand {
                 },
             ])
             .await
-            .map_err(|_| SetupErrors::CouldNotCreateChannel)?
+            .map_err(|error| SetupErrors::CouldNotCreateChannel(error.to_string()))?
             .model()
             .await
-            .map_err(|_| SetupErrors::CouldNotCreateChannel)?;
+            .map_err(|error| SetupErrors::CouldNotCreateChannel(error.to_string()))?;
 
         info!(
             "Created channel: {} ({})",
@@ -327,24 +398,36 @@ impl SetupCommand {
         let message = context
             .client
             .create_message(channel.id)
-            .content("verification message")
+            .content(&guild_message(
+                database,
+                Some(guild_id),
+                MessageId::VerificationMessage,
+                locale,
+                &[],
+            ))
             .components(&[Component::ActionRow(
                 ActionRowBuilder::new()
                     .component(
                         ButtonBuilder::new(
                             twilight_model::channel::message::component::ButtonStyle::Primary,
                         )
-                        .label("verify")
-                        .custom_id("verify")
+                        .label(guild_message(
+                            database,
+                            Some(guild_id),
+                            MessageId::VerifyButton,
+                            locale,
+                            &[],
+                        ))
+                        .custom_id(VERIFY_PREFIX)
                         .build(),
                     )
                     .build(),
             )])
             .await
-            .map_err(|_| SetupErrors::CouldNotSendMessage)?
+            .map_err(|error| SetupErrors::CouldNotSendMessage(error.to_string()))?
             .model()
             .await
-            .map_err(|_| SetupErrors::CouldNotSendMessage)?;
+            .map_err(|error| SetupErrors::CouldNotSendMessage(error.to_string()))?;
 
         info!(
             "sent message: {} in channel {}",
@@ -363,14 +446,29 @@ impl SetupCommand {
 #[async_trait]
 impl CommandBundle for SetupCommand {
     fn definition(&self) -> Command {
-        CommandBuilder::new(
-            "setup",
-            "Automatically sets up the bot",
+        LocalizedCommandBuilder::new(
+            LocalizedText::new("setup"),
+            translations().text("command-setup-description"),
             CommandType::ChatInput,
         )
         .build()
     }
 
+    fn access(&self) -> CommandAccess {
+        CommandAccess::new()
+            .permissions(Permissions::ADMINISTRATOR)
+            .guild_only()
+    }
+
+    fn cooldown(&self) -> Option<Cooldown> {
+        // Setup creates a channel and a role, running it twice at once would make two of each
+        Some(Cooldown::new(
+            CooldownBucket::Guild,
+            1,
+            Duration::from_secs(30),
+        ))
+    }
+
     /// Execute the command
     async fn execute(
         &self,
@@ -378,103 +476,151 @@ impl CommandBundle for SetupCommand {
         data: &CommandData,
     ) -> Result<(), CommandError> {
         let Some(guild_id) = context.get_guild_id() else {
+            let locale = context.get_effective_locale();
             context
-                .reply("This command must be done in a guild!")
+                .reply(guild_message(
+                    &self.database,
+                    None,
+                    MessageId::GuildOnly,
+                    locale.as_ref(),
+                    &[],
+                ))
                 .await?;
             return Ok(());
         };
 
-        match SetupCommand::setup_verification(&context.context, guild_id).await {
+        // Creating the role, channel and message can take longer than Discord waits
+        context.defer(true).await?;
+
+        let locale = context.get_guild_locale();
+        let message = |id| guild_message(&self.database, Some(guild_id), id, locale.as_ref(), &[]);
+
+        match SetupCommand::setup_verification(
+            &context.context,
+            &self.database,
+            guild_id,
+            locale.as_ref(),
+        )
+        .await
+        {
             Ok(guild_settings) => {
                 self.database
                     .set_guild_settings(&guild_settings)
-                    .map_err(|_| CommandError::Internal("Could not save guild settings!".into()))?;
-                context.reply("Setup complete!").await?;
+                    .map_err(|error| {
+                        CommandError::failed("Could not save guild settings!", error)
+                    })?;
+                context.reply(message(MessageId::SetupComplete)).await?;
             }
-            Err(setup_errors) => match setup_errors {
-                SetupErrors::CouldNotCreateChannel => {
-                    context.reply("Could not create channel").await?;
-                }
+            Err(setup_error) => {
+                let (id, detail) = match setup_error {
+                    SetupErrors::CouldNotCreateChannel(detail) => {
+                        (MessageId::SetupChannelFailed, detail)
+                    }
+                    SetupErrors::CouldNotSendMessage(detail) => {
+                        (MessageId::SetupMessageFailed, detail)
+                    }
+                    SetupErrors::CouldNotCreateRole(detail) => (MessageId::SetupRoleFailed, detail),
+                };
 
-                SetupErrors::CouldNotSendMessage => {
-                    context.reply("Could not send message!").await?
-                }
-                SetupErrors::CouldNotCreateRole => context.reply("Could not create role").await?,
-            },
+                return Err(CommandError::failed(message(id), detail));
+            }
         }
 
         Ok(())
     }
 }
 
+/// What setup could not do, with Discord's error
 enum SetupErrors {
-    CouldNotCreateChannel,
-    CouldNotSendMessage,
-    CouldNotCreateRole,
+    CouldNotCreateChannel(String),
+    CouldNotSendMessage(String),
+    CouldNotCreateRole(String),
 }
 
-pub async fn register_setup_command(context: Arc<Context>) {
-    context
-        .client
-        .interaction(context.application_id)
-        .create_global_command()
-        .chat_input("setup", "Automatically sets up the bot")
-        .default_member_permissions(Permissions::ADMINISTRATOR)
-        .dm_permission(false)
-        .await
-        .expect("error registering setup command");
+/// Queue the role and nickname updates that apply a link in a guild
+pub fn update_user(
+    database: &Database,
+    user: &User,
+    guild_config: &GuildSettings,
+) -> SqliteResult<()> {
+    database.enqueue_job(&JobKind::AddRole {
+        guild_id: guild_config.guild_id,
+        discord_user: user.discord_user,
+        role_id: guild_config.verified_role,
+    })?;
+
+    // Users can opt out of having their Embark ID as nickname with /privacy
+    if database.should_set_nickname(user.discord_user, guild_config.guild_id) {
+        database.enqueue_job(&JobKind::SetNickname {
+            guild_id: guild_config.guild_id,
+            discord_user: user.discord_user,
+            nickname: Some(user.embark_id.to_string()),
+        })?;
+    }
 
-    info!("Registered /setup command globally");
+    Ok(())
 }
 
-pub async fn update_user(
-    client: &Client,
-    user: &User,
+/// Queue the role and nickname updates that take a link back in a guild, see `update_user`
+pub fn unlink_user(
+    database: &Database,
+    discord_user: Id<UserMarker>,
     guild_config: &GuildSettings,
-) -> Result<(), ()> {
-    let current_member = client
-        .guild_member(guild_config.guild_id, user.discord_user)
-        .await
-        .map_err(|_| ())?
-        .model()
-        .await
-        .map_err(|_| ())?;
+    had_nickname: bool,
+) -> SqliteResult<()> {
+    database.enqueue_job(&JobKind::RemoveRole {
+        guild_id: guild_config.guild_id,
+        discord_user,
+        role_id: guild_config.verified_role,
+    })?;
+
+    if had_nickname {
+        database.enqueue_job(&JobKind::SetNickname {
+            guild_id: guild_config.guild_id,
+            discord_user,
+            nickname: None,
+        })?;
+    }
 
-    let mut new_roles = current_member.roles;
-    new_roles.push(guild_config.verified_role);
+    Ok(())
+}
 
-    client
-        .update_guild_member(guild_config.guild_id, user.discord_user)
-        .nick(Some(&user.embark_id.to_string()))
-        .roles(&new_roles)
-        .await
-        .map_err(|_| ())?;
+/// Staff can see Embark IDs that are only visible to staff
+pub(crate) fn is_staff(permissions: Option<Permissions>) -> bool {
+    permissions.is_some_and(|permissions| {
+        permissions.intersects(
+            Permissions::ADMINISTRATOR | Permissions::MANAGE_GUILD | Permissions::MANAGE_NICKNAMES,
+        )
+    })
+}
 
-    Ok(())
+/// Guild wide settings can only be changed by members that can manage the guild
+pub(crate) fn can_manage_guild(permissions: Option<Permissions>) -> bool {
+    permissions.is_some_and(|permissions| {
+        permissions.intersects(Permissions::ADMINISTRATOR | Permissions::MANAGE_GUILD)
+    })
 }
 
-pub async fn reply_ephemeral(
-    context: &Arc<Context>,
-    interaction_id: Id<InteractionMarker>,
-    interaction_token: &str,
-    content: String,
-) {
-    let response = InteractionResponse {
-        kind: InteractionResponseType::ChannelMessageWithSource,
-        data: Some(InteractionResponseData {
-            content: Some(content.clone().into()),
-            flags: Some(MessageFlags::EPHEMERAL),
-            ..Default::default()
-        }),
-    };
-    let response = context
-        .client
-        .interaction(context.application_id)
-        .create_response(interaction_id, interaction_token, &response)
-        .await;
-
-    match response {
-        Ok(_) => debug!("Created response: {}", content),
-        Err(error) => error!("Could not create response: {} | Error: {}", content, error),
+/// Access of the commands that change guild wide settings, see `can_manage_guild`
+pub(crate) fn manage_guild_access() -> CommandAccess {
+    CommandAccess::new()
+        .permissions(Permissions::MANAGE_GUILD)
+        .guild_only()
+}
+
+/// The guild an admin command was used in, for members that can manage it
+pub(crate) fn managed_guild(context: &CommandContext) -> Result<Id<GuildMarker>, CommandError> {
+    let Some(guild_id) = context.get_guild_id() else {
+        return Err(CommandError::Validation(
+            "This command must be done in a guild!".into(),
+        ));
     };
+
+    if !can_manage_guild(context.get_member_permissions()) {
+        return Err(CommandError::Validation(
+            "You need the Manage Server permission for this".into(),
+        ));
+    }
+
+    Ok(guild_id)
 }
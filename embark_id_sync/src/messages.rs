@@ -0,0 +1,528 @@
+use async_trait::async_trait;
+use common::commands::autocomplete::FocusedOption;
+use common::commands::options::FromCommandData;
+use common::commands::subcommands::{Subcommand, SubcommandTree};
+use common::commands::translations::Translations;
+use common::commands::{AutocompleteChoice, CommandContext, CommandError, Locale, LocalizedText};
+use data::Database;
+use std::sync::{Arc, OnceLock};
+use twilight_model::application::interaction::application_command::CommandDataOption;
+use twilight_model::id::Id;
+use twilight_model::id::marker::GuildMarker;
+
+use crate::{EmbarkIDSync, manage_guild_access, managed_guild};
+
+/// Locale key of guild overrides that apply to every locale
+const ANY_LOCALE: &str = "default";
+/// Longest message content Discord accepts
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+/// Every message members can see, guilds can override them with `/messages set`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageId {
+    JoinNotLinked,
+    JoinAlreadyLinked,
+    ApplyLinkPrompt,
+    ApplyLinkButton,
+    ApplyLinkQueued,
+    ApplyLinkFailed,
+    ApplyLinkUnavailable,
+    InvalidEmbarkId,
+    AlreadyClaimed,
+    EmbarkIdEntered,
+    VerifyModalTitle,
+    VerifyModalLabel,
+    VerificationMessage,
+    VerifyButton,
+    SetupPrompt,
+    DigestHeader,
+    FallbackMention,
+    GuildOnly,
+    SetupComplete,
+    SetupChannelFailed,
+    SetupMessageFailed,
+    SetupRoleFailed,
+}
+
+impl MessageId {
+    pub const ALL: [MessageId; 22] = [
+        MessageId::JoinNotLinked,
+        MessageId::JoinAlreadyLinked,
+        MessageId::ApplyLinkPrompt,
+        MessageId::ApplyLinkButton,
+        MessageId::ApplyLinkQueued,
+        MessageId::ApplyLinkFailed,
+        MessageId::ApplyLinkUnavailable,
+        MessageId::InvalidEmbarkId,
+        MessageId::AlreadyClaimed,
+        MessageId::EmbarkIdEntered,
+        MessageId::VerifyModalTitle,
+        MessageId::VerifyModalLabel,
+        MessageId::VerificationMessage,
+        MessageId::VerifyButton,
+        MessageId::SetupPrompt,
+        MessageId::DigestHeader,
+        MessageId::FallbackMention,
+        MessageId::GuildOnly,
+        MessageId::SetupComplete,
+        MessageId::SetupChannelFailed,
+        MessageId::SetupMessageFailed,
+        MessageId::SetupRoleFailed,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageId::JoinNotLinked => "join_not_linked",
+            MessageId::JoinAlreadyLinked => "join_already_linked",
+            MessageId::ApplyLinkPrompt => "apply_link_prompt",
+            MessageId::ApplyLinkButton => "apply_link_button",
+            MessageId::ApplyLinkQueued => "apply_link_queued",
+            MessageId::ApplyLinkFailed => "apply_link_failed",
+            MessageId::ApplyLinkUnavailable => "apply_link_unavailable",
+            MessageId::InvalidEmbarkId => "invalid_embark_id",
+            MessageId::AlreadyClaimed => "already_claimed",
+            MessageId::EmbarkIdEntered => "embark_id_entered",
+            MessageId::VerifyModalTitle => "verify_modal_title",
+            MessageId::VerifyModalLabel => "verify_modal_label",
+            MessageId::VerificationMessage => "verification_message",
+            MessageId::VerifyButton => "verify_button",
+            MessageId::SetupPrompt => "setup_prompt",
+            MessageId::DigestHeader => "digest_header",
+            MessageId::FallbackMention => "fallback_mention",
+            MessageId::GuildOnly => "guild_only",
+            MessageId::SetupComplete => "setup_complete",
+            MessageId::SetupChannelFailed => "setup_channel_failed",
+            MessageId::SetupMessageFailed => "setup_message_failed",
+            MessageId::SetupRoleFailed => "setup_role_failed",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        MessageId::ALL.into_iter().find(|id| id.as_str() == name)
+    }
+
+    /// Placeholders that are filled in for this message
+    pub fn placeholders(&self) -> &'static [&'static str] {
+        match self {
+            MessageId::JoinNotLinked => &["guild", "channel"],
+            MessageId::JoinAlreadyLinked | MessageId::ApplyLinkPrompt => &["guild", "embark_id"],
+            MessageId::ApplyLinkQueued | MessageId::EmbarkIdEntered => &["embark_id"],
+            _ => &[],
+        }
+    }
+
+    /// The built-in text in every locale that has a translation file
+    pub fn default_text(&self) -> LocalizedText {
+        translations().text(self.as_str())
+    }
+
+    /// Longest text Discord accepts where the message is shown, with placeholders filled in
+    pub fn max_length(&self) -> usize {
+        match self {
+            // Button labels
+            MessageId::ApplyLinkButton | MessageId::VerifyButton => 80,
+            // Modal titles and text input labels
+            MessageId::VerifyModalTitle | MessageId::VerifyModalLabel => 45,
+            // Sent together with mentions
+            MessageId::FallbackMention | MessageId::DigestHeader => 1900,
+            _ => MAX_MESSAGE_LENGTH,
+        }
+    }
+
+    /// Check a guild's override before it is saved, Discord would reject it later otherwise
+    pub fn validate(&self, text: &str) -> Result<(), CommandError> {
+        if text.trim().is_empty() {
+            return Err(CommandError::Validation("The text can't be empty".into()));
+        }
+
+        if let Some(unknown) = placeholders_in(text)
+            .into_iter()
+            .find(|name| !self.placeholders().contains(name))
+        {
+            let known = self
+                .placeholders()
+                .iter()
+                .map(|placeholder| format!("`{{{}}}`", placeholder))
+                .collect::<Vec<_>>();
+
+            return Err(CommandError::Validation(format!(
+                "`{{{}}}` is not a placeholder of `{}`, it has {}",
+                unknown,
+                self.as_str(),
+                if known.is_empty() {
+                    "none".to_string()
+                } else {
+                    known.join(", ")
+                }
+            )));
+        }
+
+        // Measure with every placeholder at its longest
+        let filler = self
+            .placeholders()
+            .iter()
+            .map(|placeholder| {
+                (
+                    *placeholder,
+                    "x".repeat(placeholder_max_length(placeholder)),
+                )
+            })
+            .collect::<Vec<_>>();
+        let values = filler
+            .iter()
+            .map(|(placeholder, value)| (*placeholder, value.as_str()))
+            .collect::<Vec<_>>();
+        let length = render(text, &values).chars().count();
+
+        if length > self.max_length() {
+            return Err(CommandError::Validation(format!(
+                "`{}` can be at most {} characters with placeholders filled in, this is up to {}",
+                self.as_str(),
+                self.max_length(),
+                length
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Longest value a placeholder is filled in with
+fn placeholder_max_length(placeholder: &str) -> usize {
+    match placeholder {
+        // Discord's limit for guild names
+        "guild" => 100,
+        // Up to 16 characters, `#` and 4 digits
+        "embark_id" => 21,
+        // `<#`, a snowflake of up to 20 digits and `>`
+        "channel" => 23,
+        _ => 0,
+    }
+}
+
+/// Names of the `{name}` placeholders in a text
+fn placeholders_in(text: &str) -> Vec<&str> {
+    text.split('{')
+        .skip(1)
+        .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+        .filter(|name| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|character| character.is_ascii_alphanumeric() || character == '_')
+        })
+        .collect()
+}
+
+/// The built-in messages, loaded from `locales/*.ftl`
+pub fn translations() -> &'static Translations {
+    static TRANSLATIONS: OnceLock<Translations> = OnceLock::new();
+
+    TRANSLATIONS.get_or_init(|| {
+        Translations::new(Locale::EnglishUS)
+            .with_source(Locale::EnglishUS, include_str!("../locales/en-US.ftl"))
+            .and_then(|translations| {
+                translations.with_source(Locale::German, include_str!("../locales/de.ftl"))
+            })
+            .and_then(|translations| {
+                translations.with_source(Locale::Spanish, include_str!("../locales/es-ES.ftl"))
+            })
+            .and_then(|translations| {
+                translations.with_source(Locale::French, include_str!("../locales/fr.ftl"))
+            })
+            .expect("invalid translation file")
+    })
+}
+
+/// Fill in `{name}` placeholders, unknown placeholders are left as they are
+fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let value = rest.find('}').and_then(|end| {
+            let name = &rest[1..end];
+            values
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| (*value, end))
+        });
+
+        match value {
+            Some((value, end)) => {
+                rendered.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Resolve a message: the guild's override for the locale or its fallbacks, the guild's override
+/// for any locale, then the built-in text
+pub fn guild_message(
+    database: &Database,
+    guild_id: Option<Id<GuildMarker>>,
+    id: MessageId,
+    locale: Option<&Locale>,
+    values: &[(&str, &str)],
+) -> String {
+    let guild_override = guild_id.and_then(|guild_id| {
+        locale
+            .into_iter()
+            .flat_map(Locale::fallback_chain)
+            .find_map(|locale| database.get_guild_message(guild_id, id.as_str(), locale.as_str()))
+            .or_else(|| database.get_guild_message(guild_id, id.as_str(), ANY_LOCALE))
+    });
+
+    match guild_override {
+        Some(template) => render(&template, values),
+        None => render(id.default_text().get(locale), values),
+    }
+}
+
+impl EmbarkIDSync {
+    pub fn message(
+        &self,
+        guild_id: Option<Id<GuildMarker>>,
+        id: MessageId,
+        locale: Option<&Locale>,
+        values: &[(&str, &str)],
+    ) -> String {
+        guild_message(&self.database, guild_id, id, locale, values)
+    }
+}
+
+#[derive(FromCommandData)]
+struct MessageOptions {
+    /// The message
+    #[option(choices = MessageId::ALL.map(|id| (id.as_str(), id.as_str())))]
+    id: String,
+    /// Discord locale code such as `de` or `es-ES`, leave empty for every locale
+    #[option(autocomplete)]
+    locale: Option<String>,
+}
+
+#[derive(FromCommandData)]
+struct SetMessageOptions {
+    /// The message
+    #[option(choices = MessageId::ALL.map(|id| (id.as_str(), id.as_str())))]
+    id: String,
+    /// The new text
+    text: String,
+    /// Discord locale code such as `de` or `es-ES`, leave empty for every locale
+    #[option(autocomplete)]
+    locale: Option<String>,
+}
+
+/// The message and the locale key of its override
+fn parse_target(
+    id: &str,
+    locale: Option<&str>,
+) -> Result<(MessageId, Option<Locale>), CommandError> {
+    let Some(id) = MessageId::from_name(id) else {
+        return Err(CommandError::Validation("Unknown message".into()));
+    };
+
+    let locale = match locale {
+        Some(code) => Some(Locale::from_discord(code).ok_or_else(|| {
+            CommandError::Validation(format!("`{}` is not a Discord locale", code))
+        })?),
+        None => None,
+    };
+
+    Ok((id, locale))
+}
+
+/// Locales matching what was typed so far, there are too many for fixed choices
+fn locale_choices(focused: &FocusedOption) -> Vec<AutocompleteChoice> {
+    let input = focused.value.to_lowercase();
+
+    Locale::ALL
+        .into_iter()
+        .filter(|locale| locale.as_str().to_lowercase().starts_with(&input))
+        .map(|locale| AutocompleteChoice::new(locale.as_str(), locale.as_str()))
+        .collect()
+}
+
+/// `/messages`, customize the messages members see
+pub fn messages_command(database: Arc<Database>) -> SubcommandTree {
+    SubcommandTree::new("messages", "Customize the messages members see")
+        .subcommand(SetMessage {
+            database: Arc::clone(&database),
+        })
+        .subcommand(ResetMessage {
+            database: Arc::clone(&database),
+        })
+        .subcommand(ShowMessage { database })
+        .access(manage_guild_access())
+}
+
+struct SetMessage {
+    database: Arc<Database>,
+}
+
+#[async_trait]
+impl Subcommand for SetMessage {
+    type Options = SetMessageOptions;
+
+    fn name(&self) -> &str {
+        "set"
+    }
+
+    fn description(&self) -> &str {
+        "Override a message for this server"
+    }
+
+    async fn execute(
+        &self,
+        context: &mut CommandContext,
+        options: SetMessageOptions,
+    ) -> Result<(), CommandError> {
+        let guild_id = managed_guild(context)?;
+        let (id, locale) = parse_target(&options.id, options.locale.as_deref())?;
+        let locale_key = locale.as_ref().map_or(ANY_LOCALE, Locale::as_str);
+        id.validate(&options.text)?;
+
+        self.database
+            .set_guild_message(guild_id, id.as_str(), locale_key, &options.text)
+            .map_err(|error| CommandError::failed("Could not save message!", error))?;
+
+        context
+            .reply_ephemeral(format!("Updated `{}` ({})", id.as_str(), locale_key))
+            .await
+    }
+
+    async fn autocomplete(
+        &self,
+        _context: &CommandContext,
+        _options: &[CommandDataOption],
+        focused: &FocusedOption,
+    ) -> Result<Vec<AutocompleteChoice>, CommandError> {
+        Ok(locale_choices(focused))
+    }
+}
+
+struct ResetMessage {
+    database: Arc<Database>,
+}
+
+#[async_trait]
+impl Subcommand for ResetMessage {
+    type Options = MessageOptions;
+
+    fn name(&self) -> &str {
+        "reset"
+    }
+
+    fn description(&self) -> &str {
+        "Go back to the built-in message"
+    }
+
+    async fn execute(
+        &self,
+        context: &mut CommandContext,
+        options: MessageOptions,
+    ) -> Result<(), CommandError> {
+        let guild_id = managed_guild(context)?;
+        let (id, locale) = parse_target(&options.id, options.locale.as_deref())?;
+        let locale_key = locale.as_ref().map_or(ANY_LOCALE, Locale::as_str);
+
+        self.database
+            .remove_guild_message(guild_id, id.as_str(), locale_key)
+            .map_err(|error| CommandError::failed("Could not reset message!", error))?;
+
+        context
+            .reply_ephemeral(format!("Reset `{}` ({})", id.as_str(), locale_key))
+            .await
+    }
+
+    async fn autocomplete(
+        &self,
+        _context: &CommandContext,
+        _options: &[CommandDataOption],
+        focused: &FocusedOption,
+    ) -> Result<Vec<AutocompleteChoice>, CommandError> {
+        Ok(locale_choices(focused))
+    }
+}
+
+struct ShowMessage {
+    database: Arc<Database>,
+}
+
+#[async_trait]
+impl Subcommand for ShowMessage {
+    type Options = MessageOptions;
+
+    fn name(&self) -> &str {
+        "show"
+    }
+
+    fn description(&self) -> &str {
+        "Show a message and its placeholders"
+    }
+
+    async fn execute(
+        &self,
+        context: &mut CommandContext,
+        options: MessageOptions,
+    ) -> Result<(), CommandError> {
+        let guild_id = managed_guild(context)?;
+        let (id, locale) = parse_target(&options.id, options.locale.as_deref())?;
+        let locale_key = locale.as_ref().map_or(ANY_LOCALE, Locale::as_str);
+
+        let text = self
+            .database
+            .get_guild_message(guild_id, id.as_str(), locale_key)
+            .unwrap_or_else(|| id.default_text().get(locale.as_ref()).to_string());
+
+        let placeholders = id
+            .placeholders()
+            .iter()
+            .map(|placeholder| format!("`{{{}}}`", placeholder))
+            .collect::<Vec<_>>();
+
+        let header = format!("`{}` ({}):\n", id.as_str(), locale_key);
+        let footer = format!(
+            "\n\nPlaceholders: {}",
+            if placeholders.is_empty() {
+                "none".to_string()
+            } else {
+                placeholders.join(", ")
+            }
+        );
+
+        // An override can use up the whole message on its own
+        let room = MAX_MESSAGE_LENGTH - header.chars().count() - footer.chars().count();
+        let text = if text.chars().count() > room {
+            let mut truncated: String = text.chars().take(room - 1).collect();
+            truncated.push('…');
+            truncated
+        } else {
+            text
+        };
+
+        context
+            .reply_ephemeral(format!("{}{}{}", header, text, footer))
+            .await
+    }
+
+    async fn autocomplete(
+        &self,
+        _context: &CommandContext,
+        _options: &[CommandDataOption],
+        focused: &FocusedOption,
+    ) -> Result<Vec<AutocompleteChoice>, CommandError> {
+        Ok(locale_choices(focused))
+    }
+}
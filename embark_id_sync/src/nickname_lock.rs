@@ -0,0 +1,288 @@
+use async_trait::async_trait;
+use common::commands::access::CommandAccess;
+use common::commands::{CommandBundle, CommandContext, CommandError};
+use data::{Database, JobKind};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, error, info};
+use twilight_model::application::command::{Command, CommandType};
+use twilight_model::application::interaction::application_command::CommandData;
+use twilight_model::gateway::payload::incoming::MemberUpdate;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{GuildMarker, UserMarker};
+use twilight_util::builder::command::{
+    BooleanBuilder, CommandBuilder, IntegerBuilder, RoleBuilder,
+};
+
+use crate::audit_log::{AuditCategory, AuditEvent};
+use crate::context::Context;
+use crate::{EmbarkIDSync, manage_guild_access, managed_guild};
+
+/// Members with at least this many reversions are flagged in the log channel
+const REPEAT_OFFENDER_THRESHOLD: u32 = 3;
+
+/// What the lock did about a member whose nickname is not their Embark ID
+enum Drift {
+    /// Waiting out the grace period, further updates are left to that restore
+    Pending,
+    /// Restored, or accepted after the grace period, while the nickname was this one
+    Handled(Option<String>),
+}
+
+/// Drifted nicknames the lock already acted on
+///
+/// Role changes, including the ones the bot makes itself, send member updates too. Without this a
+/// single rename would be restored and counted as an offence once per update.
+#[derive(Default)]
+pub struct NicknameLockState {
+    drifts: Mutex<HashMap<(Id<GuildMarker>, Id<UserMarker>), Drift>>,
+}
+
+impl NicknameLockState {
+    /// Whether a restore should start for this nickname, marks it as pending if so
+    fn begin(&self, key: (Id<GuildMarker>, Id<UserMarker>), nickname: Option<&str>) -> bool {
+        let mut drifts = self.drifts.lock().unwrap();
+
+        match drifts.get(&key) {
+            Some(Drift::Pending) => false,
+            Some(Drift::Handled(handled)) if handled.as_deref() == nickname => false,
+            _ => {
+                drifts.insert(key, Drift::Pending);
+                true
+            }
+        }
+    }
+
+    /// The restore is over, updates with the same nickname are ignored from now on
+    fn finish(&self, key: (Id<GuildMarker>, Id<UserMarker>), nickname: Option<String>) {
+        self.drifts
+            .lock()
+            .unwrap()
+            .insert(key, Drift::Handled(nickname));
+    }
+
+    /// The nickname is the Embark ID again, or no longer locked
+    fn forget(&self, key: (Id<GuildMarker>, Id<UserMarker>)) {
+        self.drifts.lock().unwrap().remove(&key);
+    }
+}
+
+impl EmbarkIDSync {
+    /// Restores the Embark ID nickname of linked members in guilds with the nickname lock on
+    ///
+    /// Each rename is restored and counted at most once, no matter how many member updates follow.
+    pub async fn enforce_nickname_lock(&self, context: Arc<Context>, member_update: &MemberUpdate) {
+        let guild_id = member_update.guild_id;
+        let user_id = member_update.user.id;
+        let key = (guild_id, user_id);
+
+        let settings = self.database.get_nickname_lock_settings(guild_id);
+        if !settings.enabled {
+            self.nickname_lock.forget(key);
+            return;
+        }
+
+        let Some(user) = self.database.get_user_by_discord_id(user_id) else {
+            self.nickname_lock.forget(key);
+            return;
+        };
+
+        // Only lock nicknames the bot is allowed to set in the first place
+        if self.database.needs_consent(user_id, guild_id)
+            || !self.database.should_set_nickname(user_id, guild_id)
+        {
+            self.nickname_lock.forget(key);
+            return;
+        }
+
+        let exempt_roles = self.database.get_nickname_lock_exempt_roles(guild_id);
+        if member_update
+            .roles
+            .iter()
+            .any(|role| exempt_roles.contains(role))
+        {
+            self.nickname_lock.forget(key);
+            return;
+        }
+
+        let expected_nickname = user.embark_id.to_string();
+        if member_update.nick.as_deref() == Some(expected_nickname.as_str()) {
+            self.nickname_lock.forget(key);
+            return;
+        }
+
+        // Updates that didn't change the nickname, or arrive while a restore is pending
+        if !self.nickname_lock.begin(key, member_update.nick.as_deref()) {
+            return;
+        }
+
+        let current_nickname = if settings.grace_period == 0 {
+            // Handlers run before the cache is updated, it may still have the old nickname
+            member_update.nick.clone()
+        } else {
+            debug!(
+                "Nickname of {} in {} drifted, waiting {}s",
+                user_id, guild_id, settings.grace_period
+            );
+            tokio::time::sleep(Duration::from_secs(settings.grace_period)).await;
+
+            // They may have changed it back (or left) during the grace period
+            match context.cache.member(guild_id, user_id) {
+                Some(member) => member.nick().map(str::to_string),
+                None => {
+                    self.nickname_lock.forget(key);
+                    return;
+                }
+            }
+        };
+        if current_nickname.as_deref() == Some(expected_nickname.as_str()) {
+            self.nickname_lock.forget(key);
+            return;
+        }
+
+        // Until the restore shows up as an update, updates with this nickname are the same rename
+        self.nickname_lock.finish(key, current_nickname.clone());
+
+        let job = JobKind::SetNickname {
+            guild_id,
+            discord_user: user_id,
+            nickname: Some(expected_nickname.clone()),
+        };
+        if let Err(error) = self.database.enqueue_job(&job) {
+            error!(
+                "Could not queue restoring nickname of {}: {}",
+                user_id, error
+            );
+            return;
+        }
+
+        info!("Restored nickname of {} in {}", user_id, guild_id);
+
+        let offences = match self.database.record_nickname_offence(guild_id, user_id) {
+            Ok(offences) => offences,
+            Err(error) => {
+                error!("Could not record nickname offence: {}", error);
+                0
+            }
+        };
+
+        let mut event = AuditEvent::new(
+            AuditCategory::NicknameLock,
+            user_id,
+            "changed their nickname, it was restored",
+        )
+        .field(
+            "Changed to",
+            current_nickname.unwrap_or_else(|| member_update.user.name.clone()),
+        )
+        .field("Restored", expected_nickname)
+        .field("Reversions", offences.to_string());
+
+        if offences >= REPEAT_OFFENDER_THRESHOLD {
+            event = event.field("Flag", "Repeat offender");
+        }
+
+        self.audit(&context, Some(guild_id), event);
+    }
+}
+
+pub struct NicknameLockCommand {
+    database: Arc<Database>,
+}
+
+impl NicknameLockCommand {
+    pub fn new(database: Arc<Database>) -> Self {
+        NicknameLockCommand { database }
+    }
+}
+
+#[async_trait]
+impl CommandBundle for NicknameLockCommand {
+    fn definition(&self) -> Command {
+        CommandBuilder::new(
+            "nicklock",
+            "Restore Embark ID nicknames when linked members change them",
+            CommandType::ChatInput,
+        )
+        .option(BooleanBuilder::new("enabled", "Turn the nickname lock on or off").required(true))
+        .option(
+            IntegerBuilder::new(
+                "grace_seconds",
+                "Seconds a changed nickname is tolerated before it is restored",
+            )
+            .min_value(0)
+            .max_value(3600)
+            .required(false),
+        )
+        .option(
+            RoleBuilder::new(
+                "exempt_role",
+                "Members with this role may rename themselves",
+            )
+            .required(false),
+        )
+        .option(
+            RoleBuilder::new("unexempt_role", "Remove a role from the exempt list").required(false),
+        )
+        .build()
+    }
+
+    fn access(&self) -> CommandAccess {
+        manage_guild_access()
+    }
+
+    async fn execute(
+        &self,
+        context: &mut CommandContext,
+        data: &CommandData,
+    ) -> Result<(), CommandError> {
+        let guild_id = managed_guild(context)?;
+
+        let mut settings = self.database.get_nickname_lock_settings(guild_id);
+
+        if let Some(enabled) = context.get_boolean_option("enabled", data) {
+            settings.enabled = enabled;
+        }
+        if let Some(grace_period) = context.get_integer_option("grace_seconds", data) {
+            settings.grace_period = grace_period.max(0) as u64;
+        }
+
+        self.database
+            .set_nickname_lock_settings(&settings)
+            .map_err(|error| {
+                CommandError::failed("Could not save nickname lock settings!", error)
+            })?;
+
+        if let Some(role_id) = context.get_role_option("exempt_role", data) {
+            self.database
+                .add_nickname_lock_exempt_role(guild_id, role_id)
+                .map_err(|error| CommandError::failed("Could not save exempt role!", error))?;
+        }
+        if let Some(role_id) = context.get_role_option("unexempt_role", data) {
+            self.database
+                .remove_nickname_lock_exempt_role(guild_id, role_id)
+                .map_err(|error| CommandError::failed("Could not remove exempt role!", error))?;
+        }
+
+        let exempt_roles = self
+            .database
+            .get_nickname_lock_exempt_roles(guild_id)
+            .iter()
+            .map(|role_id| format!("<@&{}>", role_id))
+            .collect::<Vec<_>>();
+
+        context
+            .reply_ephemeral(format!(
+                "Nickname lock: `{}`\nGrace period: `{}s`\nExempt roles: {}",
+                if settings.enabled { "on" } else { "off" },
+                settings.grace_period,
+                if exempt_roles.is_empty() {
+                    "none".to_string()
+                } else {
+                    exempt_roles.join(", ")
+                }
+            ))
+            .await
+    }
+}
@@ -0,0 +1,160 @@
+use async_trait::async_trait;
+use common::commands::options::FromCommandData;
+use common::commands::{CommandBundle, CommandContext, CommandError};
+use data::{Database, EmbarkIDVisibility, JobKind, NicknamePreference};
+use std::sync::Arc;
+use tracing::error;
+use twilight_model::application::command::{Command, CommandType};
+use twilight_model::application::interaction::application_command::CommandData;
+use twilight_model::id::Id;
+use twilight_model::id::marker::UserMarker;
+use twilight_util::builder::command::CommandBuilder;
+
+use crate::context::Context;
+
+#[derive(FromCommandData)]
+struct PrivacyOptions {
+    /// Let the bot set your nickname to your Embark ID
+    #[option(
+        choice("Always", NicknamePreference::Always.as_str()),
+        choice("Never", NicknamePreference::Never.as_str()),
+        choice("Per server", NicknamePreference::PerGuild.as_str())
+    )]
+    nickname: Option<String>,
+    /// With per server nicknames: allow the nickname in this server
+    nickname_here: Option<bool>,
+    /// Who can look up your Embark ID
+    #[option(
+        choice("Everyone", EmbarkIDVisibility::Everyone.as_str()),
+        choice("Staff only", EmbarkIDVisibility::StaffOnly.as_str()),
+        choice("Nobody", EmbarkIDVisibility::Nobody.as_str())
+    )]
+    visibility: Option<String>,
+}
+
+pub struct PrivacyCommand {
+    database: Arc<Database>,
+}
+
+impl PrivacyCommand {
+    pub fn new(database: Arc<Database>) -> Self {
+        PrivacyCommand { database }
+    }
+}
+
+#[async_trait]
+impl CommandBundle for PrivacyCommand {
+    fn definition(&self) -> Command {
+        let mut command = CommandBuilder::new(
+            "privacy",
+            "Choose how your linked Embark ID is used",
+            CommandType::ChatInput,
+        )
+        .build();
+        command.options = PrivacyOptions::options();
+        command
+    }
+
+    async fn execute(
+        &self,
+        context: &mut CommandContext,
+        data: &CommandData,
+    ) -> Result<(), CommandError> {
+        let options = PrivacyOptions::from_command_data(data)?;
+        let Some(user_id) = context.get_user_id() else {
+            return Err(CommandError::Internal("Interaction without a user".into()));
+        };
+
+        let mut settings = self.database.get_privacy_settings(user_id);
+
+        if let Some(nickname) = options.nickname {
+            settings.nickname = NicknamePreference::from_name(&nickname)
+                .ok_or_else(|| CommandError::Validation("Unknown nickname option".into()))?;
+        }
+
+        if let Some(visibility) = options.visibility {
+            settings.visibility = EmbarkIDVisibility::from_name(&visibility)
+                .ok_or_else(|| CommandError::Validation("Unknown visibility option".into()))?;
+        }
+
+        self.database
+            .set_privacy_settings(&settings)
+            .map_err(|error| CommandError::failed("Could not save privacy settings!", error))?;
+
+        if let Some(allowed) = options.nickname_here {
+            let Some(guild_id) = context.get_guild_id() else {
+                return Err(CommandError::Validation(
+                    "`nickname_here` can only be used in a server".into(),
+                ));
+            };
+
+            self.database
+                .set_guild_nickname_preference(user_id, guild_id, allowed)
+                .map_err(|error| CommandError::failed("Could not save privacy settings!", error))?;
+        }
+
+        reset_withdrawn_nicknames(&context.context, &self.database, user_id);
+
+        let nickname_here = match (settings.nickname, context.get_guild_id()) {
+            (NicknamePreference::PerGuild, Some(guild_id)) => {
+                if self.database.should_set_nickname(user_id, guild_id) {
+                    " (allowed in this server)"
+                } else {
+                    " (not allowed in this server)"
+                }
+            }
+            _ => "",
+        };
+
+        context
+            .reply_ephemeral(format!(
+                "Nickname: `{}`{}\nEmbark ID visible to: `{}`",
+                settings.nickname.as_str(),
+                nickname_here,
+                settings.visibility.as_str()
+            ))
+            .await
+    }
+}
+
+/// Queue removing the Embark ID nickname in guilds the user no longer allows it in
+///
+/// Only nicknames that are still the Embark ID are reset, the bot set those and a nickname the
+/// member picked themselves is left alone.
+fn reset_withdrawn_nicknames(context: &Context, database: &Database, user_id: Id<UserMarker>) {
+    let Some(user) = database.get_user_by_discord_id(user_id) else {
+        return;
+    };
+    let Some(guild_ids) = context.cache.user_guilds(user_id) else {
+        return;
+    };
+    let embark_id = user.embark_id.to_string();
+
+    for guild_id in guild_ids.iter().copied() {
+        if database.get_guild_settings(&guild_id).is_none()
+            || database.should_set_nickname(user_id, guild_id)
+        {
+            continue;
+        }
+
+        let has_embark_id_nickname = context
+            .cache
+            .member(guild_id, user_id)
+            .is_some_and(|member| member.nick() == Some(embark_id.as_str()));
+        if !has_embark_id_nickname {
+            continue;
+        }
+
+        let job = JobKind::SetNickname {
+            guild_id,
+            discord_user: user_id,
+            nickname: None,
+        };
+        if let Err(error) = database.enqueue_job(&job) {
+            error!(
+                "Could not queue resetting nickname of {} in {}: {}",
+                user_id, guild_id, error
+            );
+        }
+    }
+}
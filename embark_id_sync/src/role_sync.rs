@@ -0,0 +1,217 @@
+use async_trait::async_trait;
+use common::commands::access::CommandAccess;
+use common::commands::{CommandBundle, CommandContext, CommandError};
+use data::{Database, JobKind, RolePolicy};
+use std::sync::Arc;
+use tracing::{error, info};
+use twilight_model::application::command::{Command, CommandType};
+use twilight_model::application::interaction::application_command::CommandData;
+use twilight_model::gateway::payload::incoming::MemberRemove;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{GuildMarker, RoleMarker, UserMarker};
+use twilight_util::builder::command::{CommandBuilder, StringBuilder};
+
+use crate::audit_log::{AuditCategory, AuditEvent};
+use crate::context::Context;
+use crate::{EmbarkIDSync, manage_guild_access, managed_guild};
+
+impl EmbarkIDSync {
+    /// Keeps the verified role in line with the link according to the guild's role policy
+    pub async fn sync_verified_role(
+        &self,
+        context: &Arc<Context>,
+        guild_id: Id<GuildMarker>,
+        user_id: Id<UserMarker>,
+        roles: &[Id<RoleMarker>],
+    ) {
+        let Some(guild_settings) = self.database.get_guild_settings(&guild_id) else {
+            return;
+        };
+
+        // The bot is about to change the role itself, the roles don't tell anything yet
+        if self.database.has_pending_role_job(guild_id, user_id) {
+            return;
+        }
+
+        let has_role = roles.contains(&guild_settings.verified_role);
+        // A link that is waiting for consent is not applied in this guild yet
+        let linked = self.database.get_user_by_discord_id(user_id).is_some()
+            && !self.database.needs_consent(user_id, guild_id);
+
+        if has_role == linked {
+            if self.database.get_role_override(guild_id, user_id).is_some() {
+                if let Err(error) = self.database.remove_role_override(guild_id, user_id) {
+                    error!("Could not remove role override: {}", error);
+                }
+                self.record_audit(
+                    Some(guild_id),
+                    user_id,
+                    "override_cleared",
+                    "verified role matches the link again",
+                );
+            }
+            return;
+        }
+
+        let policy = self.database.get_role_policy(guild_id);
+
+        match (linked, policy) {
+            (true, RolePolicy::Readd | RolePolicy::Strict) => {
+                let job = JobKind::AddRole {
+                    guild_id,
+                    discord_user: user_id,
+                    role_id: guild_settings.verified_role,
+                };
+                if let Err(error) = self.database.enqueue_job(&job) {
+                    error!(
+                        "Could not queue re-adding verified role to {}: {}",
+                        user_id, error
+                    );
+                    return;
+                }
+
+                info!("Re-added verified role to {} in {}", user_id, guild_id);
+                self.audit(
+                    context,
+                    Some(guild_id),
+                    AuditEvent::new(
+                        AuditCategory::RoleChange,
+                        user_id,
+                        "is linked and got the verified role back",
+                    ),
+                );
+            }
+            (false, RolePolicy::Strict) => {
+                let job = JobKind::RemoveRole {
+                    guild_id,
+                    discord_user: user_id,
+                    role_id: guild_settings.verified_role,
+                };
+                if let Err(error) = self.database.enqueue_job(&job) {
+                    error!(
+                        "Could not queue stripping verified role from {}: {}",
+                        user_id, error
+                    );
+                    return;
+                }
+
+                info!("Stripped verified role from {} in {}", user_id, guild_id);
+                self.audit(
+                    context,
+                    Some(guild_id),
+                    AuditEvent::new(
+                        AuditCategory::RoleChange,
+                        user_id,
+                        "is not linked and the verified role was removed",
+                    ),
+                );
+            }
+            _ => {
+                // Only record the override once, not for every unrelated member update
+                if self.database.get_role_override(guild_id, user_id) == Some(has_role) {
+                    return;
+                }
+
+                if let Err(error) = self.database.set_role_override(guild_id, user_id, has_role) {
+                    error!("Could not save role override: {}", error);
+                    return;
+                }
+
+                let description = if has_role {
+                    "was given the verified role by hand without a link"
+                } else {
+                    "is linked and the verified role was removed by hand"
+                };
+                self.audit(
+                    context,
+                    Some(guild_id),
+                    AuditEvent::new(AuditCategory::AdminOverride, user_id, description),
+                );
+            }
+        }
+    }
+
+    pub async fn member_remove(&self, member_remove: &MemberRemove) {
+        let guild_id = member_remove.guild_id;
+        let user_id = member_remove.user.id;
+
+        if self.database.get_guild_settings(&guild_id).is_none() {
+            return;
+        }
+
+        let had_override = self.database.get_role_override(guild_id, user_id).is_some();
+        if had_override {
+            if let Err(error) = self.database.remove_role_override(guild_id, user_id) {
+                error!("Could not remove role override: {}", error);
+            }
+        }
+
+        if had_override || self.database.get_user_by_discord_id(user_id).is_some() {
+            self.record_audit(Some(guild_id), user_id, "member_left", "left the guild");
+        }
+    }
+}
+
+pub struct RolePolicyCommand {
+    database: Arc<Database>,
+}
+
+impl RolePolicyCommand {
+    pub fn new(database: Arc<Database>) -> Self {
+        RolePolicyCommand { database }
+    }
+}
+
+#[async_trait]
+impl CommandBundle for RolePolicyCommand {
+    fn definition(&self) -> Command {
+        CommandBuilder::new(
+            "rolepolicy",
+            "Choose what happens when the verified role is changed by hand",
+            CommandType::ChatInput,
+        )
+        .option(
+            StringBuilder::new("policy", "The role policy")
+                .choices([
+                    ("Give it back to linked members", RolePolicy::Readd.as_str()),
+                    (
+                        "Accept and record overrides",
+                        RolePolicy::RecordOverride.as_str(),
+                    ),
+                    (
+                        "Strict: also strip it from unlinked members",
+                        RolePolicy::Strict.as_str(),
+                    ),
+                ])
+                .required(true),
+        )
+        .build()
+    }
+
+    fn access(&self) -> CommandAccess {
+        manage_guild_access()
+    }
+
+    async fn execute(
+        &self,
+        context: &mut CommandContext,
+        data: &CommandData,
+    ) -> Result<(), CommandError> {
+        let guild_id = managed_guild(context)?;
+
+        let Some(policy) = context
+            .get_string_option("policy", data)
+            .and_then(|policy| RolePolicy::from_name(&policy))
+        else {
+            return Err(CommandError::Validation("Unknown role policy".into()));
+        };
+
+        self.database
+            .set_role_policy(guild_id, policy)
+            .map_err(|error| CommandError::failed("Could not save role policy!", error))?;
+
+        context
+            .reply_ephemeral(format!("Role policy is now `{}`", policy.as_str()))
+            .await
+    }
+}
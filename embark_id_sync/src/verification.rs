@@ -0,0 +1,236 @@
+use async_trait::async_trait;
+use common::commands::components::{ComponentHandler, CustomId};
+use common::commands::cooldowns::{Cooldown, CooldownBucket};
+use common::commands::modals::{FormHandler, Modal, ModalField, ModalForm, ModalValues};
+use common::commands::{CommandContext, CommandError};
+use data::{EmbarkID, User};
+use std::time::Duration;
+use tracing::info;
+use twilight_model::application::interaction::message_component::MessageComponentInteractionData;
+
+use crate::EmbarkIDSync;
+use crate::audit_log::{AuditCategory, AuditEvent};
+use crate::messages::MessageId;
+
+/// Custom id of the button in the verification channel
+pub const VERIFY_PREFIX: &str = "verify";
+/// Custom id of the verification modal
+const VERIFY_MODAL_PREFIX: &str = "embark_verification";
+/// Custom id of the Embark ID input
+const EMBARK_ID_FIELD: &str = "embark_verification";
+
+/// Opens the verification modal
+pub struct VerifyButton {
+    sync: EmbarkIDSync,
+}
+
+impl VerifyButton {
+    pub fn new(sync: EmbarkIDSync) -> Self {
+        VerifyButton { sync }
+    }
+}
+
+#[async_trait]
+impl ComponentHandler for VerifyButton {
+    fn prefix(&self) -> &str {
+        VERIFY_PREFIX
+    }
+
+    fn cooldown(&self) -> Option<Cooldown> {
+        Some(Cooldown::new(
+            CooldownBucket::User,
+            3,
+            Duration::from_secs(60),
+        ))
+    }
+
+    async fn handle(
+        &self,
+        context: &mut CommandContext,
+        _custom_id: &CustomId,
+        _data: &MessageComponentInteractionData,
+    ) -> Result<(), CommandError> {
+        let guild_id = context.get_guild_id();
+        let locale = context.get_effective_locale();
+        let message = |id| self.sync.message(guild_id, id, locale.as_ref(), &[]);
+
+        let modal = Modal::<VerifyForm>::new(
+            CustomId::new(VERIFY_MODAL_PREFIX),
+            message(MessageId::VerifyModalTitle),
+        )
+        .label(EMBARK_ID_FIELD, message(MessageId::VerifyModalLabel))
+        .placeholder(EMBARK_ID_FIELD, "name#1234");
+
+        context.open_modal(modal).await
+    }
+}
+
+/// The verification modal
+pub struct VerifyForm {
+    embark_id: String,
+}
+
+impl ModalForm for VerifyForm {
+    // According to Embark's website names are 2 to 16 characters, plus 5 for #1234
+    const FIELDS: &'static [ModalField] =
+        &[ModalField::short(EMBARK_ID_FIELD).length(2 + 5, 16 + 5)];
+
+    fn from_values(values: &ModalValues<'_>) -> Result<Self, CommandError> {
+        Ok(VerifyForm {
+            embark_id: values.required(EMBARK_ID_FIELD)?.trim().to_string(),
+        })
+    }
+}
+
+/// Links the Embark ID entered in the verification modal
+pub struct VerifyModal {
+    sync: EmbarkIDSync,
+}
+
+impl VerifyModal {
+    pub fn new(sync: EmbarkIDSync) -> Self {
+        VerifyModal { sync }
+    }
+}
+
+#[async_trait]
+impl FormHandler for VerifyModal {
+    type Form = VerifyForm;
+
+    fn prefix(&self) -> &str {
+        VERIFY_MODAL_PREFIX
+    }
+
+    fn cooldown(&self) -> Option<Cooldown> {
+        // Every submission hits the database and queues role and nickname updates
+        Some(Cooldown::new(
+            CooldownBucket::User,
+            5,
+            Duration::from_secs(60),
+        ))
+    }
+
+    async fn submit(
+        &self,
+        context: &mut CommandContext,
+        _custom_id: &CustomId,
+        form: VerifyForm,
+    ) -> Result<(), CommandError> {
+        let sync = &self.sync;
+        let guild_id = context.get_guild_id();
+        let locale = context.get_effective_locale();
+
+        let Some(discord_user) = context.get_user_id() else {
+            return Err(CommandError::Validation("Unknown user".into()));
+        };
+        let input = form.embark_id.as_str();
+
+        let Ok(embark_id) = EmbarkID::new(input) else {
+            sync.audit(
+                &context.context,
+                guild_id,
+                AuditEvent::new(
+                    AuditCategory::FailedClaim,
+                    discord_user,
+                    "entered an invalid Embark ID",
+                )
+                .field("Input", input),
+            );
+
+            return context
+                .reply_ephemeral(sync.message(
+                    guild_id,
+                    MessageId::InvalidEmbarkId,
+                    locale.as_ref(),
+                    &[],
+                ))
+                .await;
+        };
+
+        if let Some(embark_user_profile) = sync.database.get_user_by_embark_id(&embark_id) {
+            sync.audit(
+                &context.context,
+                guild_id,
+                AuditEvent::new(
+                    AuditCategory::DuplicateClaim,
+                    discord_user,
+                    "tried to claim an Embark ID that is already linked",
+                )
+                .field("Embark ID", embark_id.to_string())
+                .field(
+                    "Linked to",
+                    format!("<@{}>", embark_user_profile.discord_user),
+                ),
+            );
+
+            return context
+                .reply_ephemeral(sync.message(
+                    guild_id,
+                    MessageId::AlreadyClaimed,
+                    locale.as_ref(),
+                    &[],
+                ))
+                .await;
+        }
+
+        let user = User {
+            discord_user,
+            embark_id: embark_id.clone(),
+        };
+
+        // Submitting again replaces the previous link
+        match sync.database.get_user_by_discord_id(user.discord_user) {
+            Some(previous) => {
+                sync.database
+                    .update_user_embark_id(user.discord_user, &embark_id)
+                    .map_err(|error| CommandError::failed("Could not update link!", error))?;
+
+                sync.audit(
+                    &context.context,
+                    guild_id,
+                    AuditEvent::new(
+                        AuditCategory::Relink,
+                        user.discord_user,
+                        "changed their Embark ID",
+                    )
+                    .field("Previous", previous.embark_id.to_string())
+                    .field("New", embark_id.to_string()),
+                );
+            }
+            None => {
+                sync.database
+                    .add_user(&user)
+                    .map_err(|error| CommandError::failed("Could not save link!", error))?;
+
+                sync.audit(
+                    &context.context,
+                    guild_id,
+                    AuditEvent::new(
+                        AuditCategory::Link,
+                        user.discord_user,
+                        "linked their Embark ID",
+                    )
+                    .field("Embark ID", embark_id.to_string()),
+                );
+            }
+        }
+
+        info!("{} entered {}", discord_user, embark_id.to_string());
+
+        if let Some(guild_settings) =
+            guild_id.and_then(|guild_id| sync.database.get_guild_settings(&guild_id))
+        {
+            sync.sync_member(&context.context, &user, &guild_settings);
+        }
+
+        // Only confirm once the link is saved, a failure is reported with a reference instead
+        context
+            .reply_ephemeral(sync.message(
+                guild_id,
+                MessageId::EmbarkIdEntered,
+                locale.as_ref(),
+                &[("embark_id", &embark_id.to_string())],
+            ))
+            .await
+    }
+}
@@ -0,0 +1,104 @@
+use async_trait::async_trait;
+use common::commands::options::FromCommandData;
+use common::commands::pagination::Paginator;
+use common::commands::{CommandBundle, CommandContext, CommandError};
+use data::{AuditScope, Database};
+use std::sync::Arc;
+use twilight_model::application::command::{Command, CommandType};
+use twilight_model::application::interaction::application_command::CommandData;
+use twilight_model::id::Id;
+use twilight_model::id::marker::UserMarker;
+use twilight_util::builder::command::CommandBuilder;
+
+use crate::audit_log::AuditTrailPages;
+use crate::is_staff;
+
+#[derive(FromCommandData)]
+struct WhoisOptions {
+    /// The member to look up
+    user: Id<UserMarker>,
+    /// Show the link history of the member instead
+    history: Option<bool>,
+}
+
+pub struct WhoisCommand {
+    database: Arc<Database>,
+}
+
+impl WhoisCommand {
+    pub fn new(database: Arc<Database>) -> Self {
+        WhoisCommand { database }
+    }
+}
+
+#[async_trait]
+impl CommandBundle for WhoisCommand {
+    fn definition(&self) -> Command {
+        let mut command = CommandBuilder::new(
+            "whois",
+            "Look up the Embark ID linked to a member",
+            CommandType::ChatInput,
+        )
+        .build();
+        command.options = WhoisOptions::options();
+        command
+    }
+
+    async fn execute(
+        &self,
+        context: &mut CommandContext,
+        data: &CommandData,
+    ) -> Result<(), CommandError> {
+        let options = WhoisOptions::from_command_data(data)?;
+        let target = options.user;
+        let Some(viewer) = context.get_user_id() else {
+            return Err(CommandError::Internal("Interaction without a user".into()));
+        };
+        let viewer_is_staff = is_staff(context.get_member_permissions());
+        let visible = self
+            .database
+            .get_privacy_settings(target)
+            .visible_to(viewer, viewer_is_staff);
+
+        if options.history.unwrap_or(false) {
+            let scope = if viewer == target {
+                AuditScope::User(target)
+            } else {
+                // Staff only see what happened in their own guild
+                let Some(guild_id) = context.get_guild_id().filter(|_| viewer_is_staff) else {
+                    return context
+                        .reply_ephemeral("Only staff can see the link history of other members")
+                        .await;
+                };
+                if !visible {
+                    return context
+                        .reply_ephemeral(format!("No Embark ID to show for <@{}>", target))
+                        .await;
+                }
+
+                AuditScope::UserInGuild(target, guild_id)
+            };
+
+            let pages = AuditTrailPages::new(Arc::clone(&self.database), scope, "Link history");
+            return Paginator::new(pages)
+                .empty(format!("<@{}> has no link history", target))
+                .send(context, true)
+                .await;
+        }
+
+        // Unlinked and private members get the same reply, it must not tell whether a link exists
+        let visible_user = self
+            .database
+            .get_user_by_discord_id(target)
+            .filter(|_| visible);
+        let Some(user) = visible_user else {
+            return context
+                .reply_ephemeral(format!("No Embark ID to show for <@{}>", target))
+                .await;
+        };
+
+        context
+            .reply_ephemeral(format!("<@{}> is `{}`", target, user.embark_id.to_string()))
+            .await
+    }
+}
@@ -0,0 +1,67 @@
+//! The command definitions sent to Discord, compared with the golden files in
+//! `tests/golden/commands`
+//!
+//! Missing or changed golden files fail the test. After an intended change, rerun with `BLESS=1`
+//! to write them and review the diff.
+
+use common::commands::definition::{deployed_definition, validate_command};
+use common::handler::Handler;
+use data::Database;
+use embark_id_sync::EmbarkIDSync;
+use std::path::PathBuf;
+use std::sync::Arc;
+use twilight_model::application::command::Command;
+
+fn definitions() -> Vec<Command> {
+    let database = Arc::new(Database::new(":memory:").expect("Could not open the database"));
+    EmbarkIDSync::new(database)
+        .commands()
+        .iter()
+        .map(|registration| deployed_definition(registration.command.as_ref()))
+        .collect()
+}
+
+#[test]
+fn every_command_is_valid() {
+    for definition in definitions() {
+        if let Err(error) = validate_command(&definition) {
+            panic!("{}", error);
+        }
+    }
+}
+
+#[test]
+fn definitions_match_golden_files() {
+    let bless = std::env::var_os("BLESS").is_some_and(|value| value == "1");
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/commands");
+    if bless {
+        std::fs::create_dir_all(&directory).expect("Could not create the golden file directory");
+    }
+
+    let mut changed = Vec::new();
+
+    for definition in definitions() {
+        // Through `Value` so localizations are written in a stable order
+        let value = serde_json::to_value(&definition).expect("Could not serialize definition");
+        let json =
+            serde_json::to_string_pretty(&value).expect("Could not serialize definition") + "\n";
+        let path = directory.join(format!("{}.json", definition.name));
+
+        match std::fs::read_to_string(&path) {
+            Ok(golden) if golden == json => {}
+            _ if bless => std::fs::write(&path, json).expect("Could not write golden file"),
+            Ok(_) => changed.push(definition.name),
+            Err(_) => changed.push(format!("{} (missing)", definition.name)),
+        }
+    }
+
+    assert!(
+        changed.is_empty(),
+        "definitions of {} don't match their golden files, rerun with BLESS=1 to accept them",
+        changed
+            .iter()
+            .map(|name| format!("/{}", name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+}
@@ -0,0 +1,107 @@
+use common::commands::Locale;
+use embark_id_sync::{MessageId, translations};
+
+#[test]
+fn every_locale_translates_every_message() {
+    let missing = translations().missing_keys();
+
+    assert!(
+        missing.is_empty(),
+        "missing translations: {}",
+        missing
+            .iter()
+            .map(|(locale, key)| format!("{} in {}", key, locale.as_str()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+}
+
+#[test]
+fn every_message_has_a_default() {
+    for id in MessageId::ALL {
+        assert!(
+            translations().get(id.as_str(), None).is_some(),
+            "{} is missing from {}",
+            id.as_str(),
+            Locale::EnglishUS.as_str()
+        );
+    }
+}
+
+#[test]
+fn regional_locales_fall_back() {
+    let key = MessageId::InvalidEmbarkId.as_str();
+
+    assert_eq!(
+        translations().get(key, Some(&Locale::SpanishLatinAmerica)),
+        translations().get(key, Some(&Locale::Spanish))
+    );
+    assert_eq!(
+        translations().get(key, Some(&Locale::Polish)),
+        translations().get(key, None)
+    );
+}
+
+#[test]
+fn built_in_messages_pass_override_checks() {
+    for id in MessageId::ALL {
+        for locale in [
+            Locale::EnglishUS,
+            Locale::German,
+            Locale::Spanish,
+            Locale::French,
+        ] {
+            let text = translations()
+                .get(id.as_str(), Some(&locale))
+                .unwrap_or_default();
+
+            assert!(
+                id.validate(text).is_ok(),
+                "{} in {} would be rejected as an override",
+                id.as_str(),
+                locale.as_str()
+            );
+        }
+    }
+}
+
+#[test]
+fn overrides_are_checked_against_discord_limits() {
+    assert!(MessageId::VerifyButton.validate(&"x".repeat(80)).is_ok());
+    assert!(MessageId::VerifyButton.validate(&"x".repeat(81)).is_err());
+    assert!(
+        MessageId::VerifyModalTitle
+            .validate(&"x".repeat(46))
+            .is_err()
+    );
+    assert!(MessageId::VerifyButton.validate("  ").is_err());
+
+    // Placeholders count at their longest, a guild name can be 100 characters
+    assert!(
+        MessageId::JoinNotLinked
+            .validate(&format!("{{guild}}{}", "x".repeat(1900)))
+            .is_ok()
+    );
+    assert!(
+        MessageId::JoinNotLinked
+            .validate(&format!("{{guild}}{}", "x".repeat(1901)))
+            .is_err()
+    );
+}
+
+#[test]
+fn overrides_only_use_known_placeholders() {
+    assert!(
+        MessageId::JoinNotLinked
+            .validate("Welcome to {guild}, see {channel}")
+            .is_ok()
+    );
+    assert!(
+        MessageId::JoinNotLinked
+            .validate("Welcome {embark_id}")
+            .is_err()
+    );
+    assert!(MessageId::VerifyButton.validate("Verify {guild}").is_err());
+    // Braces that aren't placeholders are left alone
+    assert!(MessageId::VerifyButton.validate("Verify { now }").is_ok());
+}